@@ -0,0 +1,177 @@
+//! Serves a recorded session over the `Instrument` and `Application` gRPC services, so clients
+//! can inspect it as if the app was running.
+//!
+//! Sessions are recorded with `subscriber::Builder::record_to`. While replaying, the playback
+//! can be controlled by writing commands to stdin, one per line:
+//!
+//! - `pause` and `resume`
+//! - `step [count]` pauses the playback and releases the next update(s), an empty line steps once
+//! - `speed <factor>` changes the playback speed, e.g. `speed 10` replays ten times as fast
+
+mod playback;
+mod recording;
+mod server;
+mod zeroconf;
+
+use playback::{Control, Playback};
+use recording::Recording;
+use server::Server;
+use std::{
+    io::{self, BufRead},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    process,
+    sync::Arc,
+    thread,
+};
+use tokio::sync::mpsc;
+use zeroconf::Zeroconf;
+
+const USAGE: &str = "usage: replay <recording> [--addr <addr>] [--speed <factor>] [--paused]";
+
+/// The port live apps listen on by default, so clients find the replay in the same place.
+const DEFAULT_PORT: u16 = 6669;
+
+struct Args {
+    path: PathBuf,
+    addr: SocketAddr,
+    speed: f64,
+    paused: bool,
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let recording = match Recording::load(&args.path) {
+        Ok(recording) => Arc::new(recording),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    };
+
+    if let Some(e) = &recording.truncated {
+        eprintln!("{e}, the rest of the recording is skipped");
+    }
+
+    let package_info = recording.header.package_info.clone().unwrap_or_default();
+    println!(
+        "replaying {} updates ({:.1}s) of {} {}",
+        recording.updates.len(),
+        recording.duration().as_secs_f64(),
+        package_info.name,
+        package_info.version
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .expect("replay runtime initialization failed");
+
+    runtime.block_on(async move {
+        let playback = Playback::new(recording.clone(), args.paused, args.speed);
+        let server = Server::new(args.addr, recording, playback.subscribe());
+
+        let (controls_tx, controls) = mpsc::channel(16);
+        thread::Builder::new()
+            .name("replay_controls".into())
+            .spawn(move || read_controls(controls_tx))
+            .expect("replay could not spawn thread");
+
+        // discovery is best effort, clients can still connect to the address directly
+        let _mdns = Zeroconf::new(args.addr.port(), package_info)
+            .register()
+            .map_err(|e| eprintln!("failed to advertise the replay through mDNS: {e}"))
+            .ok();
+
+        tokio::spawn(playback.run(controls));
+
+        println!("listening on {}", args.addr);
+        if let Err(e) = server.serve().await {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    });
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut path = None;
+        let mut addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), DEFAULT_PORT);
+        let mut speed = 1.0;
+        let mut paused = false;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+            match arg.as_str() {
+                "--addr" => {
+                    addr = value()?
+                        .parse()
+                        .map_err(|e| format!("invalid address: {e}"))?
+                }
+                "--speed" => speed = parse_speed(&value()?)?,
+                "--paused" => paused = true,
+                _ if arg.starts_with("--") || path.is_some() => {
+                    return Err(format!("unexpected argument `{arg}`"))
+                }
+                _ => path = Some(PathBuf::from(arg)),
+            }
+        }
+
+        Ok(Self {
+            path: path.ok_or("missing recording")?,
+            addr,
+            speed,
+            paused,
+        })
+    }
+}
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!(
+            "invalid speed `{value}`, expected a positive number"
+        )),
+    }
+}
+
+/// Reads playback controls from stdin until it is closed.
+fn read_controls(controls: mpsc::Sender<Control>) {
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            return;
+        };
+
+        let mut words = line.split_whitespace();
+        let control = match (words.next(), words.next()) {
+            (None, _) => Ok(Control::Step(1)),
+            (Some("pause"), None) => Ok(Control::Pause),
+            (Some("resume"), None) => Ok(Control::Resume),
+            (Some("step"), None) => Ok(Control::Step(1)),
+            (Some("step"), Some(count)) => count
+                .parse()
+                .map(Control::Step)
+                .map_err(|_| format!("invalid count `{count}`")),
+            (Some("speed"), Some(speed)) => parse_speed(speed).map(Control::Speed),
+            _ => Err(format!("unknown command `{line}`")),
+        };
+
+        match control {
+            Ok(control) => {
+                if controls.blocking_send(control).is_err() {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::recording::Recording;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, watch},
+    time::Instant,
+};
+
+/// Changes how the recording is played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Pause,
+    Resume,
+    /// Pauses the playback and releases the given number of updates.
+    Step(usize),
+    /// Sets the speed relative to the recording, e.g. `2.0` plays back twice as fast.
+    Speed(f64),
+}
+
+/// The playback position shared by all clients.
+///
+/// All clients see the same point in time of the recording, a client connecting later first
+/// receives all updates released so far, just like it would receive a snapshot from a live app.
+pub struct Playback {
+    recording: Arc<Recording>,
+    /// The number of updates released to clients.
+    position: watch::Sender<usize>,
+    paused: bool,
+    speed: f64,
+    /// When the next update is released while playing.
+    deadline: Instant,
+    /// How long until the next update is released, kept while paused.
+    remaining: Duration,
+}
+
+impl Playback {
+    pub fn new(recording: Arc<Recording>, paused: bool, speed: f64) -> Self {
+        let (position, _) = watch::channel(0);
+
+        Self {
+            recording,
+            position,
+            paused,
+            speed,
+            deadline: Instant::now(),
+            remaining: Duration::ZERO,
+        }
+    }
+
+    /// Returns a receiver for the number of released updates.
+    pub fn subscribe(&self) -> watch::Receiver<usize> {
+        self.position.subscribe()
+    }
+
+    pub async fn run(mut self, mut controls: mpsc::Receiver<Control>) {
+        let mut controls_closed = false;
+        self.reset_delay();
+
+        loop {
+            let finished = *self.position.borrow() == self.recording.updates.len();
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(self.deadline), if !self.paused && !finished => {
+                    self.release(1);
+                }
+
+                control = controls.recv(), if !controls_closed => match control {
+                    Some(control) => self.control(control),
+                    // keep playing back without controls
+                    None => controls_closed = true,
+                },
+
+                // keep serving the released updates once the playback is over
+                else => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Applies a control, keeping the time already waited for the next update.
+    fn control(&mut self, control: Control) {
+        let now = Instant::now();
+
+        match control {
+            Control::Pause => self.pause(now),
+            Control::Resume => {
+                if self.paused {
+                    self.paused = false;
+                    self.deadline = now + self.remaining;
+                }
+            }
+            Control::Step(count) => {
+                self.pause(now);
+                self.release(count);
+            }
+            Control::Speed(speed) => {
+                let factor = self.speed / speed;
+                self.speed = speed;
+
+                if self.paused {
+                    self.remaining = self.remaining.mul_f64(factor);
+                } else {
+                    self.deadline =
+                        now + self.deadline.saturating_duration_since(now).mul_f64(factor);
+                }
+            }
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        if !self.paused {
+            self.paused = true;
+            self.remaining = self.deadline.saturating_duration_since(now);
+        }
+    }
+
+    /// Starts waiting for the full gap before the next update.
+    fn reset_delay(&mut self) {
+        let position = *self.position.borrow();
+        self.remaining = self.recording.gap(position).div_f64(self.speed);
+        self.deadline = Instant::now() + self.remaining;
+    }
+
+    fn release(&mut self, count: usize) {
+        let len = self.recording.updates.len();
+
+        let released = self.position.send_if_modified(|position| {
+            let previous = *position;
+            *position = (previous + count).min(len);
+
+            if *position == len && previous < len {
+                println!("end of recording reached");
+            }
+
+            *position != previous
+        });
+
+        if released {
+            self.reset_delay();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    /// A recording of `len` updates published a second apart.
+    fn recording(len: u64) -> Arc<Recording> {
+        let updates = (0..len)
+            .map(|secs| wire::instrument::Update {
+                now: Some((SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).into()),
+                ..Default::default()
+            })
+            .collect();
+
+        Arc::new(Recording {
+            header: wire::recording::Header::default(),
+            updates,
+            truncated: None,
+        })
+    }
+
+    fn spawn(playback: Playback) -> (mpsc::Sender<Control>, watch::Receiver<usize>) {
+        let position = playback.subscribe();
+        let (controls_tx, controls) = mpsc::channel(16);
+        tokio::spawn(playback.run(controls));
+
+        (controls_tx, position)
+    }
+
+    /// Lets the playback handle the sent controls and the timers that are due.
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Sends a control and lets the playback handle it.
+    async fn send(controls: &mpsc::Sender<Control>, control: Control) {
+        controls.send(control).await.unwrap();
+        settle().await;
+    }
+
+    /// Moves the paused clock forward and lets the playback react.
+    async fn advance(millis: u64) {
+        tokio::time::advance(Duration::from_millis(millis)).await;
+        settle().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn steps_while_paused() {
+        let (controls, position) = spawn(Playback::new(recording(5), true, 1.0));
+        advance(10_000).await;
+        assert_eq!(*position.borrow(), 0);
+
+        send(&controls, Control::Step(2)).await;
+        assert_eq!(*position.borrow(), 2);
+
+        // stepping past the end stops at the last update
+        send(&controls, Control::Step(10)).await;
+        assert_eq!(*position.borrow(), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pausing_keeps_the_elapsed_wait() {
+        let (controls, position) = spawn(Playback::new(recording(5), false, 1.0));
+        settle().await;
+        assert_eq!(*position.borrow(), 1);
+
+        advance(500).await;
+        send(&controls, Control::Pause).await;
+        advance(10_000).await;
+        assert_eq!(*position.borrow(), 1);
+
+        send(&controls, Control::Resume).await;
+        advance(499).await;
+        assert_eq!(*position.borrow(), 1);
+        advance(1).await;
+        assert_eq!(*position.borrow(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speed_changes_scale_the_remaining_wait() {
+        let (controls, position) = spawn(Playback::new(recording(5), false, 1.0));
+        settle().await;
+        assert_eq!(*position.borrow(), 1);
+
+        advance(500).await;
+
+        // the remaining 500ms of the gap take 250ms at twice the speed
+        send(&controls, Control::Speed(2.0)).await;
+        advance(249).await;
+        assert_eq!(*position.borrow(), 1);
+        advance(1).await;
+        assert_eq!(*position.borrow(), 2);
+
+        advance(500).await;
+        assert_eq!(*position.borrow(), 3);
+    }
+}
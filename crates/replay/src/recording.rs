@@ -0,0 +1,239 @@
+use prost::Message;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// A recorded session, see `subscriber::Builder::record_to`.
+pub struct Recording {
+    pub header: wire::recording::Header,
+    pub updates: Vec<wire::instrument::Update>,
+    /// Why the end of the last file couldn't be decoded, e.g. because the app exited while an
+    /// update was written. The updates before it are loaded.
+    pub truncated: Option<Error>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read `{}`: {1}", .0.display())]
+    Io(PathBuf, io::Error),
+    #[error("failed to decode `{}`: {1}", .0.display())]
+    Decode(PathBuf, prost::DecodeError),
+    /// The recording was written by an incompatible version of the devtools.
+    #[error(
+        "`{}` uses version {1} of the recording format, but only version {} is supported",
+        .0.display(),
+        wire::recording::PROTOCOL_VERSION
+    )]
+    UnsupportedVersion(PathBuf, u32),
+    /// A file is named like a different part than its header says.
+    #[error("`{}` contains part {2} of a recording, expected part {1}", .0.display())]
+    WrongPart(PathBuf, u32, u32),
+    /// A file was left behind by a different recording to the same path.
+    #[error("`{}` belongs to a recording started at a different time", .0.display())]
+    ForeignPart(PathBuf),
+}
+
+impl Recording {
+    /// Loads all files of the recording at `path`, in order.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut header: Option<wire::recording::Header> = None;
+        let mut updates = Vec::new();
+        let mut truncated = None;
+
+        for part in 0.. {
+            let part_path = wire::recording::part_path(path, part);
+
+            let bytes = match fs::read(&part_path) {
+                Ok(bytes) => bytes,
+                // the first file missing is an error, a later one marks the end of the recording
+                Err(e) if e.kind() == io::ErrorKind::NotFound && part > 0 => break,
+                Err(e) => return Err(Error::Io(part_path, e)),
+            };
+
+            // only the end of the last file may be cut off
+            if let Some(truncated) = truncated.take() {
+                return Err(truncated);
+            }
+
+            let mut buf = bytes.as_slice();
+            let part_header = wire::recording::Header::decode_length_delimited(&mut buf)
+                .map_err(|e| Error::Decode(part_path.clone(), e))?;
+            if part_header.protocol_version != wire::recording::PROTOCOL_VERSION {
+                return Err(Error::UnsupportedVersion(
+                    part_path,
+                    part_header.protocol_version,
+                ));
+            }
+            if part_header.part != part {
+                return Err(Error::WrongPart(part_path, part, part_header.part));
+            }
+            if let Some(header) = &header {
+                if part_header.started_at != header.started_at {
+                    return Err(Error::ForeignPart(part_path));
+                }
+            }
+
+            while !buf.is_empty() {
+                match wire::instrument::Update::decode_length_delimited(&mut buf) {
+                    Ok(update) => updates.push(update),
+                    Err(e) => {
+                        truncated = Some(Error::Decode(part_path, e));
+                        break;
+                    }
+                }
+            }
+
+            header.get_or_insert(part_header);
+        }
+
+        Ok(Self {
+            // the loop returns early if there is no first file
+            header: header.unwrap(),
+            updates,
+            truncated,
+        })
+    }
+
+    /// Returns how long after the previous update the update at `index` was published.
+    pub fn gap(&self, index: usize) -> Duration {
+        let now = |index: usize| -> Option<SystemTime> {
+            self.updates.get(index)?.now.clone()?.try_into().ok()
+        };
+
+        match (index.checked_sub(1).and_then(now), now(index)) {
+            (Some(previous), Some(now)) => now.duration_since(previous).unwrap_or_default(),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns the time between the first and the last update.
+    pub fn duration(&self) -> Duration {
+        (1..self.updates.len()).map(|index| self.gap(index)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory for the files of one test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("replay-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn header(part: u32, started_at: u64) -> wire::recording::Header {
+        wire::recording::Header {
+            protocol_version: wire::recording::PROTOCOL_VERSION,
+            package_info: None,
+            started_at: Some(at(started_at)),
+            part,
+        }
+    }
+
+    fn at(secs: u64) -> prost_types::Timestamp {
+        (SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).into()
+    }
+
+    fn update(secs: u64) -> wire::instrument::Update {
+        wire::instrument::Update {
+            now: Some(at(secs)),
+            ..Default::default()
+        }
+    }
+
+    fn write_part(path: &Path, header: wire::recording::Header, updates: &[u64]) -> Vec<u8> {
+        let mut bytes = header.encode_length_delimited_to_vec();
+        for secs in updates {
+            bytes.extend(update(*secs).encode_length_delimited_to_vec());
+        }
+        fs::write(wire::recording::part_path(path, header.part), &bytes).unwrap();
+        bytes
+    }
+
+    fn times(recording: &Recording) -> Vec<u64> {
+        recording
+            .updates
+            .iter()
+            .map(|update| {
+                let now: SystemTime = update.now.clone().unwrap().try_into().unwrap();
+                now.duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loads_all_parts_in_order() {
+        let dir = TempDir::new("parts");
+        let path = dir.0.join("session.rec");
+        write_part(&path, header(0, 100), &[100, 101]);
+        write_part(&path, header(1, 100), &[103]);
+        write_part(&path, header(2, 100), &[106, 110]);
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(times(&recording), [100, 101, 103, 106, 110]);
+        assert_eq!(recording.header.part, 0);
+        assert!(recording.truncated.is_none());
+
+        assert_eq!(recording.gap(0), Duration::ZERO);
+        assert_eq!(recording.gap(2), Duration::from_secs(2));
+        assert_eq!(recording.gap(5), Duration::ZERO);
+        assert_eq!(recording.duration(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn rejects_misnamed_parts() {
+        let dir = TempDir::new("misnamed");
+        let path = dir.0.join("session.rec");
+        write_part(&path, header(0, 100), &[100]);
+        let bytes = write_part(&path, header(2, 100), &[101]);
+        fs::write(wire::recording::part_path(&path, 1), bytes).unwrap();
+
+        assert!(matches!(
+            Recording::load(&path),
+            Err(Error::WrongPart(_, 1, 2))
+        ));
+    }
+
+    #[test]
+    fn rejects_parts_of_other_recordings() {
+        let dir = TempDir::new("foreign");
+        let path = dir.0.join("session.rec");
+        write_part(&path, header(0, 100), &[100]);
+        write_part(&path, header(1, 50), &[51]);
+
+        assert!(matches!(Recording::load(&path), Err(Error::ForeignPart(_))));
+    }
+
+    #[test]
+    fn reports_truncated_updates() {
+        let dir = TempDir::new("truncated");
+        let path = dir.0.join("session.rec");
+        let bytes = write_part(&path, header(0, 100), &[100, 101]);
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(times(&recording), [100]);
+        assert!(matches!(recording.truncated, Some(Error::Decode(..))));
+
+        // only the last file may be cut off
+        write_part(&path, header(1, 100), &[102]);
+        assert!(matches!(Recording::load(&path), Err(Error::Decode(..))));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::recording::Recording;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{mpsc, watch};
+use wire::instrument::Interests;
+
+/// Serves a recording over the same gRPC services as `subscriber::Server`.
+pub struct Server {
+    addr: SocketAddr,
+    instrument: InstrumentServer,
+    application: ApplicationServer,
+}
+
+struct InstrumentServer {
+    recording: Arc<Recording>,
+    position: watch::Receiver<usize>,
+    /// The interests of all open watches, by the address of the client that opened them.
+    watches: Mutex<Vec<(SocketAddr, watch::Sender<Interests>)>>,
+}
+
+struct ApplicationServer {
+    recording: Arc<Recording>,
+    position: watch::Receiver<usize>,
+}
+
+impl Server {
+    /// The maximum capacity of updates to buffer for each subscribed client.
+    ///
+    /// Unlike a live app, clients are never disconnected for being slow, the replay of
+    /// a client simply waits until it catches up.
+    const CLIENT_BUFFER_CAPACITY: usize = 1024;
+
+    pub fn new(
+        addr: SocketAddr,
+        recording: Arc<Recording>,
+        position: watch::Receiver<usize>,
+    ) -> Self {
+        Self {
+            addr,
+            instrument: InstrumentServer {
+                recording: recording.clone(),
+                position: position.clone(),
+                watches: Mutex::default(),
+            },
+            application: ApplicationServer {
+                recording,
+                position,
+            },
+        }
+    }
+
+    pub async fn serve(self) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(wire::instrument::instrument_server::InstrumentServer::new(
+                self.instrument,
+            ))
+            .add_service(
+                wire::application::application_server::ApplicationServer::new(self.application),
+            )
+            .serve(self.addr)
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl wire::instrument::instrument_server::Instrument for InstrumentServer {
+    type WatchUpdatesStream =
+        tokio_stream::wrappers::ReceiverStream<Result<wire::instrument::Update, tonic::Status>>;
+
+    async fn watch_updates(
+        &self,
+        req: tonic::Request<wire::instrument::InstrumentRequest>,
+    ) -> Result<tonic::Response<Self::WatchUpdatesStream>, tonic::Status> {
+        let peer = req.remote_addr();
+        let req = req.into_inner();
+
+        let interests = req
+            .interests()
+            .ok_or(tonic::Status::invalid_argument("could not parse sources"))?;
+
+        // log filters are evaluated against the live spans of the app, which aren't recorded
+        if !req.log_filter.is_empty() {
+            return Err(tonic::Status::unimplemented(
+                "log filters are not supported when replaying a recording",
+            ));
+        }
+
+        let (interests_tx, interests_rx) = watch::channel(interests);
+        if let Some(peer) = peer {
+            let mut watches = self.watches.lock().unwrap();
+            watches.retain(|(_, interests)| !interests.is_closed());
+            watches.push((peer, interests_tx));
+        }
+
+        let (tx, rx) = mpsc::channel(Server::CLIENT_BUFFER_CAPACITY);
+        tokio::spawn(watch(
+            self.recording.clone(),
+            self.position.clone(),
+            interests_rx,
+            tx,
+        ));
+
+        Ok(tonic::Response::new(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        ))
+    }
+
+    async fn update_interests(
+        &self,
+        req: tonic::Request<wire::instrument::UpdateInterestsRequest>,
+    ) -> Result<tonic::Response<wire::instrument::UpdateInterestsResponse>, tonic::Status> {
+        let peer = req.remote_addr().ok_or(tonic::Status::failed_precondition(
+            "cannot update interests, the client address is unknown",
+        ))?;
+
+        let interests = Interests::from_bits(req.into_inner().interests)
+            .ok_or(tonic::Status::invalid_argument("could not parse sources"))?;
+
+        let updated = self
+            .watches
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(addr, _)| *addr == peer)
+            .filter(|(_, tx)| tx.send(interests).is_ok())
+            .count();
+
+        if updated == 0 {
+            return Err(tonic::Status::not_found(
+                "no watch was started from this connection",
+            ));
+        }
+
+        Ok(tonic::Response::new(
+            wire::instrument::UpdateInterestsResponse {},
+        ))
+    }
+
+    async fn get_diagnostics(
+        &self,
+        _req: tonic::Request<wire::instrument::GetDiagnosticsRequest>,
+    ) -> Result<tonic::Response<wire::instrument::Diagnostics>, tonic::Status> {
+        let position = *self.position.borrow();
+        let updates = &self.recording.updates[..position];
+
+        // the events dropped up to the current point in time of the recording
+        let dropped = |dropped_events: fn(&wire::instrument::Update) -> Option<u64>| -> u64 {
+            updates.iter().filter_map(dropped_events).sum()
+        };
+        let logs = dropped(|u| Some(u.log_update.as_ref()?.dropped_events));
+        let metadata = dropped(|u| Some(u.new_metadata.as_ref()?.dropped_events));
+        let ipc = dropped(|u| Some(u.ipc_update.as_ref()?.dropped_events));
+        let tasks = dropped(|u| Some(u.task_update.as_ref()?.dropped_events));
+        let spans = dropped(|u| Some(u.span_update.as_ref()?.dropped_events));
+        let events = dropped(|u| Some(u.events_update.as_ref()?.dropped_events));
+        let windows = dropped(|u| Some(u.window_update.as_ref()?.dropped_events));
+        let panics = dropped(|u| Some(u.panic_update.as_ref()?.dropped_events));
+
+        let diagnostics = wire::instrument::Diagnostics {
+            // the capacity of the recorded app isn't known
+            event_buffer_capacity: 0,
+            dropped_events: Some(wire::instrument::DroppedEvents {
+                logs,
+                metadata,
+                ipc,
+                tasks,
+                spans,
+                events,
+                windows,
+                panics,
+                total: logs + metadata + ipc + tasks + spans + events + windows + panics,
+            }),
+        };
+
+        Ok(tonic::Response::new(diagnostics))
+    }
+
+    async fn set_filter(
+        &self,
+        _req: tonic::Request<wire::instrument::SetFilterRequest>,
+    ) -> Result<tonic::Response<wire::instrument::SetFilterResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "filters cannot be changed when replaying a recording",
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl wire::application::application_server::Application for ApplicationServer {
+    async fn get_package_info(
+        &self,
+        _req: tonic::Request<wire::application::GetPackageInfoRequest>,
+    ) -> Result<tonic::Response<wire::application::PackageInfo>, tonic::Status> {
+        let package_info = self.recording.header.package_info.clone();
+
+        Ok(tonic::Response::new(package_info.unwrap_or_default()))
+    }
+
+    async fn list_windows(
+        &self,
+        _req: tonic::Request<wire::application::ListWindowsRequest>,
+    ) -> Result<tonic::Response<wire::application::ListWindowsResponse>, tonic::Status> {
+        let position = *self.position.borrow();
+
+        // every window update contains complete snapshots of the windows that changed
+        let mut windows = HashMap::new();
+        for update in &self.recording.updates[..position] {
+            if let Some(window_update) = &update.window_update {
+                for window in &window_update.windows {
+                    windows.insert(window.label.clone(), window.clone());
+                }
+            }
+        }
+
+        let mut windows: Vec<_> = windows
+            .into_values()
+            .filter(|window| window.closed_at.is_none())
+            .collect();
+        windows.sort_by_key(|window| {
+            window
+                .created_at
+                .as_ref()
+                .map(|created_at| (created_at.seconds, created_at.nanos))
+        });
+
+        Ok(tonic::Response::new(
+            wire::application::ListWindowsResponse { windows },
+        ))
+    }
+
+    async fn get_config(
+        &self,
+        _req: tonic::Request<wire::application::GetConfigRequest>,
+    ) -> Result<tonic::Response<wire::application::Config>, tonic::Status> {
+        Err(not_recorded("the app configuration"))
+    }
+
+    async fn get_plugins(
+        &self,
+        _req: tonic::Request<wire::application::GetPluginsRequest>,
+    ) -> Result<tonic::Response<wire::application::Plugins>, tonic::Status> {
+        Err(not_recorded("the app plugins"))
+    }
+
+    async fn get_versions(
+        &self,
+        _req: tonic::Request<wire::application::GetVersionsRequest>,
+    ) -> Result<tonic::Response<wire::application::Versions>, tonic::Status> {
+        Err(not_recorded("the app versions"))
+    }
+
+    async fn get_process_info(
+        &self,
+        _req: tonic::Request<wire::application::GetProcessInfoRequest>,
+    ) -> Result<tonic::Response<wire::application::ProcessInfo>, tonic::Status> {
+        Err(not_recorded("the process info"))
+    }
+}
+
+fn not_recorded(what: &str) -> tonic::Status {
+    tonic::Status::unimplemented(format!("{what} is not part of the recording"))
+}
+
+/// Sends the released updates to a client, filtered by its interests.
+async fn watch(
+    recording: Arc<Recording>,
+    mut position: watch::Receiver<usize>,
+    mut interests_rx: watch::Receiver<Interests>,
+    tx: mpsc::Sender<Result<wire::instrument::Update, tonic::Status>>,
+) {
+    let mut interests = *interests_rx.borrow_and_update();
+    let mut sent = 0;
+
+    loop {
+        let released = *position.borrow_and_update();
+        for update in &recording.updates[sent..released] {
+            if tx.send(Ok(filter_update(update, interests))).await.is_err() {
+                return;
+            }
+        }
+        sent = released;
+
+        tokio::select! {
+            changed = position.changed() => if changed.is_err() {
+                return;
+            },
+
+            changed = interests_rx.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+
+                let previous = interests;
+                interests = *interests_rx.borrow_and_update();
+
+                // replaying the sent updates for the new interests produces the same state
+                // a snapshot from a live app would
+                let new_interests = interests.difference(previous);
+                if !new_interests.is_empty() {
+                    for update in &recording.updates[..sent] {
+                        if tx.send(Ok(filter_update(update, new_interests))).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns a copy of `update` containing only the parts the client is interested in.
+fn filter_update(
+    update: &wire::instrument::Update,
+    interests: Interests,
+) -> wire::instrument::Update {
+    wire::instrument::Update {
+        now: update.now.clone(),
+        new_metadata: update
+            .new_metadata
+            .clone()
+            .filter(|_| interests.contains(Interests::Metadata)),
+        log_update: update
+            .log_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Trace)),
+        ipc_update: update
+            .ipc_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Ipc)),
+        task_update: update
+            .task_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Tasks)),
+        span_update: update
+            .span_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Spans)),
+        events_update: update
+            .events_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Events)),
+        window_update: update
+            .window_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Windows)),
+        metrics_update: update
+            .metrics_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Metrics)),
+        panic_update: update
+            .panic_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Panics)),
+        skipped_updates: update.skipped_updates,
+    }
+}
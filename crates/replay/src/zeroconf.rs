@@ -0,0 +1,32 @@
+use mdns_sd::ServiceDaemon;
+
+/// Advertises the replay like a live app, so clients discover it through mDNS.
+pub struct Zeroconf {
+    grpc_port: u16,
+    package_info: wire::application::PackageInfo,
+}
+
+impl Zeroconf {
+    pub fn new(grpc_port: u16, package_info: wire::application::PackageInfo) -> Self {
+        Self {
+            grpc_port,
+            package_info,
+        }
+    }
+
+    /// Publishes the service, the returned daemon has to be kept alive while serving.
+    ///
+    /// The platform of the recorded app isn't known, so the service describes the replaying
+    /// machine.
+    pub fn register(self) -> Result<ServiceDaemon, Box<dyn std::error::Error + Send + Sync>> {
+        let mdns = ServiceDaemon::new()?;
+
+        // the name is shown in the session list, so mark replays as such
+        let instance_name = format!("{} (replay)", self.package_info.name);
+        let service =
+            wire::zeroconf::service_info(&instance_name, self.grpc_port, &self.package_info)?;
+        mdns.register(service)?;
+
+        Ok(mdns)
+    }
+}
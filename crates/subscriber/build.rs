@@ -0,0 +1,16 @@
+use std::process::Command;
+
+fn main() {
+    // the version of the compiler building the app, reported by the `GetVersions` RPC
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=DEVTOOLS_RUSTC_VERSION={}", version.trim());
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
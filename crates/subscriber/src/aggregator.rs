@@ -1,10 +1,20 @@
 use crate::{
-    id_map::IdMap, stats, util::TimeAnchor, Command, Event, Include, Shared, ToProto, Unsent, Watch,
+    id_map::IdMap,
+    log_filter::{LogFilter, ScopeSpan},
+    log_history::{LogEntry, LogHistory},
+    metrics::Sampler,
+    retention::{Completed, Completions, Retention},
+    stats,
+    util::TimeAnchor,
+    Command, DroppedEvents, Event, Include, Shared, ToProto, Unsent, Watch,
 };
 use wire::instrument::Interests;
 use futures::FutureExt;
+use prost::Message;
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
     mem,
+    net::SocketAddr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -18,11 +28,31 @@ pub struct Aggregator {
     commands: mpsc::Receiver<Command>,
     shared: Arc<Shared>,
 
+    /// How often updates are published to watchers.
+    publish_interval: Duration,
+
+    /// How often process metrics are sampled.
+    metrics_interval: Duration,
+
+    /// The maximum value for the duration histograms of the command stats.
+    command_duration_max: Duration,
+
+    /// How much data about completed requests, tasks and log events is kept.
+    retention: Retention,
+    /// Completed entries that may be evicted according to `retention`, by kind.
+    completed_ipc_requests: Completions<tracing_core::span::Id>,
+    completed_tasks: Completions<tracing_core::span::Id>,
+    completed_resources: Completions<tracing_core::span::Id>,
+    completed_async_ops: Completions<tracing_core::span::Id>,
+    completed_tauri_events: Completions<tracing_core::span::Id>,
+    closed_windows: Completions<String>,
+
     /// Which sources should be tracked
     /// enabled: Sources,
     watchers: Vec<Watch<wire::instrument::Update>>,
 
-    /// *All* metadata for task spans and user-defined spans that we care about.
+    /// *All* metadata for task spans and user-defined spans that we care about
+    /// and that was already published.
     ///
     /// This is sent to new clients as part of the initial state.
     all_metadata: Vec<wire::register_metadata::NewMetadata>,
@@ -32,10 +62,66 @@ pub struct Aggregator {
     /// This is emptied on every state update.
     new_metadata: Vec<wire::register_metadata::NewMetadata>,
 
-    log_events: Vec<wire::log::Event>,
+    /// IDs of all metadata in `all_metadata` and `new_metadata`.
+    ///
+    /// Callsites may be registered more than once (e.g. when the interest cache is rebuilt),
+    /// this makes sure every callsite is sent to a client exactly once.
+    metadata_ids: HashSet<u64>,
+
+    /// Recent log events, replayed to new clients.
+    log_history: LogHistory,
 
     ipc_requests: IdMap<IPCRequest>,
     ipc_request_stats: IdMap<Arc<stats::IPCRequestStats>>,
+    /// Captured arguments and responses, only populated when payload capture is enabled.
+    ipc_request_payloads: IdMap<IPCPayloads>,
+    /// Requests that have not completed yet, and so aren't part of their command's stats.
+    incomplete_ipc_requests: HashSet<tracing_core::span::Id>,
+    /// Stats of all completed requests by command, these are kept for the whole session.
+    command_stats: HashMap<String, stats::CommandStats>,
+
+    tasks: IdMap<Task>,
+    task_stats: IdMap<Arc<stats::TaskStats>>,
+    /// Tasks, resources and async operations that have not been dropped yet.
+    live_tasks: HashSet<tracing_core::span::Id>,
+    live_resources: HashSet<tracing_core::span::Id>,
+    live_async_ops: HashSet<tracing_core::span::Id>,
+
+    /// Resources created by the async runtime, e.g. timers and semaphores.
+    resources: IdMap<Resource>,
+    resource_stats: IdMap<Arc<stats::ResourceStats>>,
+
+    /// Async operations on resources, e.g. waiting for a timer.
+    async_ops: IdMap<AsyncOp>,
+    async_op_stats: IdMap<Arc<stats::AsyncOpStats>>,
+
+    /// Emitted Tauri events, by the ID of their `event.emit` span.
+    tauri_events: IdMap<TauriEvent>,
+
+    /// All open windows and recently closed windows, by label.
+    windows: HashMap<String, Window>,
+    /// Window events recorded since the last update.
+    ///
+    /// These are only buffered while there are watchers, new watchers receive `windows` instead.
+    window_events: Vec<wire::windows::WindowEvent>,
+
+    sampler: Sampler,
+    /// Recent process metrics, replayed to new clients.
+    metrics: VecDeque<wire::metrics::Sample>,
+    /// The number of samples at the end of `metrics` that were not published yet.
+    unsent_metrics: usize,
+
+    /// Recent panics, replayed to new clients.
+    panics: VecDeque<wire::panics::PanicEvent>,
+    /// The number of panics at the end of `panics` that were not published yet.
+    unsent_panics: usize,
+
+    /// Span events recorded since the last update.
+    ///
+    /// These are only buffered while there are watchers, new watchers receive `open_spans` instead.
+    span_events: Vec<wire::spans::SpanEvent>,
+    /// Spans that have not been closed yet, including all values recorded so far.
+    open_spans: HashMap<tracing_core::span::Id, OpenSpan>,
 
     /// Used to anchor monotonic timestamps to a base `SystemTime`, to produce a
     /// timestamp that can be sent over the wire.
@@ -56,38 +142,202 @@ struct IPCRequest {
     kind: wire::ipc::request::Kind,
     fields: Vec<wire::Field>,
     handler: Option<wire::Location>,
+    window: Option<String>,
+    /// Encoded size of the request, used to enforce the retention limits.
+    size: usize,
+}
+
+#[derive(Default)]
+struct IPCPayloads {
+    is_dirty: AtomicBool,
+    args: Vec<wire::ipc::Payload>,
+    response: Option<wire::ipc::Payload>,
+    /// Encoded size of all payloads, counted towards the request's size for the retention limits.
+    size: usize,
+}
+
+struct Task {
+    id: tracing_core::span::Id,
+    is_dirty: AtomicBool,
+    metadata: &'static tracing_core::Metadata<'static>,
+    kind: wire::tasks::task::Kind,
+    fields: Vec<wire::Field>,
+    parents: Vec<wire::SpanId>,
+    location: wire::Location,
+    /// Encoded size of the task, used to enforce the retention limits.
+    size: usize,
+}
+
+struct Resource {
+    id: tracing_core::span::Id,
+    is_dirty: AtomicBool,
+    metadata: &'static tracing_core::Metadata<'static>,
+    kind: String,
+    concrete_type: String,
+    fields: Vec<wire::Field>,
+    location: wire::Location,
+    parent: Option<tracing_core::span::Id>,
+    is_internal: bool,
+    /// Encoded size of the resource, used to enforce the retention limits.
+    size: usize,
+}
+
+struct AsyncOp {
+    id: tracing_core::span::Id,
+    is_dirty: AtomicBool,
+    metadata: &'static tracing_core::Metadata<'static>,
+    source: String,
+    resource: tracing_core::span::Id,
+    /// Encoded size of the async operation, used to enforce the retention limits.
+    size: usize,
+}
+
+struct TauriEvent {
+    id: tracing_core::span::Id,
+    is_dirty: AtomicBool,
+    metadata: &'static tracing_core::Metadata<'static>,
+    name: String,
+    source_window: Option<String>,
+    target_window: Option<String>,
+    payload_len: u64,
+    emitted_at: Instant,
+    fields: Vec<wire::Field>,
+    /// All deliveries so far, the first `sent_deliveries` of them were already published.
+    deliveries: Vec<wire::events::Delivery>,
+    sent_deliveries: usize,
+    /// When the `event.emit` span was closed, no more deliveries are recorded after that.
+    completed_at: Option<Instant>,
+    /// Encoded size of the event and its deliveries, used to enforce the retention limits.
+    size: usize,
+}
+
+struct Window {
+    label: String,
+    /// Whether the window changed since the last update.
+    is_dirty: bool,
+    url: String,
+    focused: bool,
+    size: Option<wire::windows::Size>,
+    created_at: Instant,
+    closed_at: Option<Instant>,
+}
+
+struct OpenSpan {
+    at: prost_types::Timestamp,
+    parent: Option<wire::SpanId>,
+    metadata: &'static tracing_core::Metadata<'static>,
+    /// All values recorded so far, shared with the log events emitted inside the span.
+    fields: Arc<Vec<wire::Field>>,
+    /// Whether the span's creation is still buffered in `span_events`.
+    is_unsent: bool,
+}
+
+/// The log events of an update, before they are filtered for each watcher.
+struct LogUpdate {
+    entries: Vec<LogEntry>,
+    dropped_events: u64,
 }
 
 impl Aggregator {
     /// Default frequency for publishing events to clients.
-    const DEFAULT_PUBLISH_INTERVAL: Duration = Duration::from_millis(250);
+    pub const DEFAULT_PUBLISH_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Default frequency for sampling process metrics.
+    pub const DEFAULT_METRICS_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Default maximum value for the duration histograms of the command stats.
+    pub const DEFAULT_COMMAND_DURATION_MAX: Duration = Duration::from_secs(60);
+
+    /// The maximum number of process metrics samples replayed to new clients.
+    const METRICS_HISTORY_CAPACITY: usize = 600;
+
+    /// The maximum number of panics replayed to new clients.
+    const PANIC_HISTORY_CAPACITY: usize = 100;
+
+    /// The maximum number of open spans that are tracked.
+    ///
+    /// Spans whose close event was dropped would be tracked forever otherwise, so the oldest
+    /// quarter of them is evicted when this is exceeded.
+    const MAX_OPEN_SPANS: usize = 10_000;
 
     pub(crate) fn new(
         shared: Arc<Shared>,
         events: mpsc::Receiver<Event>,
         commands: mpsc::Receiver<Command>,
+        publish_interval: Duration,
+        metrics_interval: Duration,
+        retention: Retention,
+        log_history: LogHistory,
     ) -> Self {
         Self {
             shared,
             events,
             commands,
+            publish_interval,
+            metrics_interval,
+            command_duration_max: Self::DEFAULT_COMMAND_DURATION_MAX,
+            retention,
+            completed_ipc_requests: Completions::new(),
+            completed_tasks: Completions::new(),
+            completed_resources: Completions::new(),
+            completed_async_ops: Completions::new(),
+            completed_tauri_events: Completions::new(),
+            closed_windows: Completions::new(),
             base_time: TimeAnchor::new(),
             watchers: Vec::new(),
             all_metadata: Vec::new(),
             new_metadata: Vec::new(),
-            log_events: Vec::new(),
+            metadata_ids: HashSet::new(),
+            log_history,
             ipc_requests: IdMap::new(),
             ipc_request_stats: IdMap::new(),
+            ipc_request_payloads: IdMap::new(),
+            incomplete_ipc_requests: HashSet::new(),
+            command_stats: HashMap::new(),
+            tasks: IdMap::new(),
+            task_stats: IdMap::new(),
+            live_tasks: HashSet::new(),
+            live_resources: HashSet::new(),
+            live_async_ops: HashSet::new(),
+            resources: IdMap::new(),
+            resource_stats: IdMap::new(),
+            async_ops: IdMap::new(),
+            async_op_stats: IdMap::new(),
+            tauri_events: IdMap::new(),
+            windows: HashMap::new(),
+            window_events: Vec::new(),
+            sampler: Sampler::default(),
+            metrics: VecDeque::new(),
+            unsent_metrics: 0,
+            panics: VecDeque::new(),
+            unsent_panics: 0,
+            span_events: Vec::new(),
+            open_spans: HashMap::new(),
+        }
+    }
+
+    /// Sets the maximum value for the duration histograms of the command stats.
+    pub(crate) fn with_command_duration_max(self, command_duration_max: Duration) -> Self {
+        Self {
+            command_duration_max,
+            ..self
         }
     }
 
     pub async fn run(mut self) {
-        let mut interval = tokio::time::interval(Self::DEFAULT_PUBLISH_INTERVAL);
+        let mut interval = tokio::time::interval(self.publish_interval);
+        let mut metrics_interval = tokio::time::interval(self.metrics_interval);
 
         loop {
             let should_publish = tokio::select! {
                 _ = interval.tick() => true,
 
+                _ = metrics_interval.tick() => {
+                    self.sample_metrics();
+
+                    false
+                }
+
                 _ = self.shared.flush.should_flush.notified() => {
                     tracing::debug!("approaching capacity; draining buffer");
 
@@ -99,6 +349,15 @@ impl Aggregator {
                         Some(Command::Instrument(watcher)) => {
                             self.add_instrument_watcher(watcher)
                         },
+                        Some(Command::UpdateInterests { peer, interests, updated }) => {
+                            let _ = updated.send(self.update_interests(peer, interests));
+                        },
+                        Some(Command::ListWindows { windows }) => {
+                            let _ = windows.send(self.open_windows());
+                        },
+                        Some(Command::ListCommands { commands }) => {
+                            let _ = commands.send(self.invoked_commands());
+                        },
                         None => {
                             tracing::debug!("rpc channel closed, terminating");
                             return;
@@ -125,12 +384,14 @@ impl Aggregator {
                 };
             }
 
+            self.aggregate_completed();
+
             // flush data to clients, if there are any currently subscribed
             // watchers and we should send a new update.
             if !self.watchers.is_empty() && should_publish {
                 self.publish();
             }
-            // self.cleanup_closed();
+            self.cleanup_closed();
             if drained {
                 self.shared.flush.has_flushed();
             }
@@ -140,52 +401,166 @@ impl Aggregator {
     fn add_instrument_watcher(&mut self, watcher: Watch<wire::instrument::Update>) {
         tracing::debug!("new instrument watcher");
 
+        // publish pending data first, so the initial state doesn't overlap with the next update.
+        // Without watchers, publishing would consume the dropped event counts without reporting them
+        if !self.watchers.is_empty() {
+            self.publish();
+        }
+
+        let update = self.initial_update(watcher.interests, &watcher.log_filter);
+
+        // Send the initial state --- if this fails, the watcher is already dead
+        if watcher.update(update) {
+            self.watchers.push(watcher);
+            // self.enabled |= sources;
+        }
+    }
+
+    /// Replaces the interests of all watchers opened by `peer`, returning how many were updated.
+    ///
+    /// Watchers that gained new interests are sent the full state for these right away,
+    /// since they missed all incremental updates while uninterested.
+    fn update_interests(&mut self, peer: SocketAddr, interests: Interests) -> usize {
+        // publish pending data with the old interests, so the full state doesn't overlap with the next update
+        if !self.watchers.is_empty() {
+            self.publish();
+        }
+
+        let mut updated = 0;
+
+        for idx in 0..self.watchers.len() {
+            if self.watchers[idx].peer != Some(peer) {
+                continue;
+            }
+
+            let added = interests.difference(self.watchers[idx].interests);
+            self.watchers[idx].interests = interests;
+            updated += 1;
+
+            if !added.is_empty() {
+                let log_filter = self.watchers[idx].log_filter.clone();
+                let update = self.initial_update(added, &log_filter);
+                // if this fails the watcher is dead and will be removed on the next publish
+                self.watchers[idx].update(update);
+            }
+        }
+
+        updated
+    }
+
+    /// Builds an update containing the full state for the given interests.
+    fn initial_update(
+        &mut self,
+        interests: Interests,
+        log_filter: &LogFilter,
+    ) -> wire::instrument::Update {
         let now = Instant::now();
 
-        let new_metadata =
-            watcher
-                .interests
-                .contains(Interests::Metadata)
-                .then_some(wire::RegisterMetadata {
-                    metadata: self.all_metadata.clone(),
-                });
+        let new_metadata = interests
+            .contains(Interests::Metadata)
+            .then(|| wire::RegisterMetadata {
+                metadata: self.all_metadata.clone(),
+                dropped_events: 0,
+            });
 
-        let log_update = watcher
-            .interests
+        let log_update = interests
             .contains(Interests::Trace)
-            .then_some(self.log_update(Include::All));
+            .then(|| self.log_update(Include::All).to_proto(log_filter));
+
+        let ipc_update = interests
+            .contains(Interests::Ipc)
+            .then(|| self.ipc_update(Include::All));
+
+        let task_update = interests
+            .contains(Interests::Tasks)
+            .then(|| self.task_update(Include::All));
+
+        let span_update = interests
+            .contains(Interests::Spans)
+            .then(|| self.span_update(Include::All));
+
+        let events_update = interests
+            .contains(Interests::Events)
+            .then(|| self.events_update(Include::All));
+
+        let window_update = interests
+            .contains(Interests::Windows)
+            .then(|| self.window_update(Include::All));
+
+        let metrics_update = interests
+            .contains(Interests::Metrics)
+            .then(|| self.metrics_update(Include::All));
 
-        let ipc_update = Some(self.ipc_update(Include::All));
+        let panic_update = interests
+            .contains(Interests::Panics)
+            .then(|| self.panic_update(Include::All));
 
-        let update = &wire::instrument::Update {
+        wire::instrument::Update {
             new_metadata,
             log_update,
             ipc_update,
+            task_update,
+            span_update,
+            events_update,
+            window_update,
+            metrics_update,
+            panic_update,
             now: Some(self.base_time.to_timestamp(now)),
-        };
-
-        // Send the initial state --- if this fails, the watcher is already dead
-        if watcher.update(update) {
-            self.watchers.push(watcher);
-            // self.enabled |= sources;
+            skipped_updates: 0,
         }
     }
 
     fn update_state(&mut self, event: Event) {
-        match event {
-            Event::Metadata(meta) => {
-                self.all_metadata.push(meta.into());
-                self.new_metadata.push(meta.into());
+        // metadata dropped when its callsite was registered is sent with the first event using it
+        if let Some(metadata) = event.metadata() {
+            let meta_id = wire::MetaId::from(metadata);
+            if self.metadata_ids.insert(meta_id.id) {
+                self.new_metadata.push(metadata.into());
             }
+        }
+
+        match event {
+            // registered above
+            Event::Metadata(_) => {}
             Event::LogEvent {
                 metadata,
                 fields,
                 at,
-            } => self.log_events.push(wire::log::Event {
-                metadata_id: Some(metadata.into()),
-                fields,
-                at: Some(self.base_time.to_timestamp(at)),
-            }),
+                parents,
+                ipc_request,
+            } => {
+                let scope = parents
+                    .iter()
+                    .filter_map(|parent| {
+                        let open_span = self
+                            .open_spans
+                            .get(&tracing_core::span::Id::from_u64(parent.id))?;
+
+                        Some(ScopeSpan {
+                            metadata: open_span.metadata,
+                            fields: open_span.fields.clone(),
+                        })
+                    })
+                    .collect();
+
+                let event = wire::log::Event {
+                    metadata_id: Some(metadata.into()),
+                    fields,
+                    at: Some(self.base_time.to_timestamp(at)),
+                    parents,
+                    ipc_request,
+                };
+
+                self.log_history.push(
+                    at,
+                    LogEntry {
+                        event,
+                        metadata,
+                        scope,
+                    },
+                    !self.watchers.is_empty(),
+                );
+            }
             Event::IPCRequestInitiated {
                 id,
                 stats,
@@ -194,37 +569,371 @@ impl Aggregator {
                 handler,
                 cmd,
                 kind,
+                window,
             } => {
-                self.ipc_requests.insert(
-                    id.clone(),
-                    IPCRequest {
-                        id: id.clone(),
-                        is_dirty: AtomicBool::new(true),
-                        cmd,
-                        metadata,
+                let mut request = IPCRequest {
+                    id: id.clone(),
+                    is_dirty: AtomicBool::new(true),
+                    cmd,
+                    metadata,
+                    fields,
+                    kind,
+                    handler: Some(handler),
+                    window,
+                    size: 0,
+                };
+                request.size = request.to_proto(&self.base_time).encoded_len()
+                    + stats.to_proto(&self.base_time).encoded_len();
+
+                self.ipc_requests.insert(id.clone(), request);
+                self.incomplete_ipc_requests.insert(id.clone());
+
+                self.ipc_request_stats.insert(id, stats);
+            }
+            Event::IPCPayload {
+                request,
+                is_response,
+                payload,
+            } => {
+                // the request may have been dropped because the event buffer was full
+                if self.ipc_requests.get(&request).is_none() {
+                    return;
+                }
+
+                let payloads = self.ipc_request_payloads.get_or_default(request);
+                payloads.size += payload.encoded_len();
+                if is_response {
+                    payloads.response = Some(payload);
+                } else {
+                    payloads.args.push(payload);
+                }
+                payloads.is_dirty.store(true, Ordering::Release);
+            }
+            Event::TauriEventEmitted {
+                id,
+                metadata,
+                fields,
+                name,
+                source_window,
+                target_window,
+                payload_len,
+                at,
+            } => {
+                let mut tauri_event = TauriEvent {
+                    id: id.clone(),
+                    is_dirty: AtomicBool::new(true),
+                    metadata,
+                    name,
+                    source_window,
+                    target_window,
+                    payload_len,
+                    emitted_at: at,
+                    fields,
+                    deliveries: Vec::new(),
+                    sent_deliveries: 0,
+                    completed_at: None,
+                    size: 0,
+                };
+                tauri_event.size = tauri_event.to_proto(&self.base_time).encoded_len();
+
+                self.tauri_events.insert(id, tauri_event);
+            }
+            Event::TauriEventDelivered { event, window, at } => {
+                // the event may have been dropped because the event buffer was full
+                let Some(tauri_event) = self.tauri_events.get_mut(&event) else {
+                    return;
+                };
+
+                let delivery = wire::events::Delivery {
+                    event: Some(event.into()),
+                    window,
+                    delivered_at: Some(self.base_time.to_timestamp(at)),
+                    latency: at
+                        .saturating_duration_since(tauri_event.emitted_at)
+                        .try_into()
+                        .ok(),
+                };
+
+                tauri_event.size += delivery.encoded_len();
+                tauri_event.deliveries.push(delivery);
+            }
+            Event::Window { window, kind, at } => {
+                use wire::windows::window_event::Kind;
+
+                // a new window may reuse the label of a closed one
+                if matches!(kind, Kind::Created(_)) {
+                    self.windows.remove(&window);
+                }
+
+                // windows whose creation was dropped are tracked from their first event
+                let state = self.windows.entry(window.clone()).or_insert_with(|| Window {
+                    label: window.clone(),
+                    is_dirty: true,
+                    url: String::new(),
+                    focused: false,
+                    size: None,
+                    created_at: at,
+                    closed_at: None,
+                });
+
+                match &kind {
+                    Kind::Created(created) => {
+                        state.url = created.url.clone();
+                        state.size = created.size.clone();
+                    }
+                    Kind::Focused(focused) => state.focused = focused.focused,
+                    Kind::Resized(resized) => state.size = resized.size.clone(),
+                    Kind::Closed(_) => {
+                        state.focused = false;
+                        state.closed_at = Some(at);
+                    }
+                    Kind::Navigated(navigated) => state.url = navigated.url.clone(),
+                }
+                state.is_dirty = true;
+
+                if matches!(kind, Kind::Closed(_)) {
+                    self.closed_windows.insert(Completed {
+                        key: window.clone(),
+                        completed_at: at,
+                        size: state.to_proto(&self.base_time).encoded_len(),
+                    });
+                }
+
+                // only buffered for current watchers, new watchers receive the windows instead
+                if !self.watchers.is_empty() {
+                    self.window_events.push(wire::windows::WindowEvent {
+                        window,
+                        at: Some(self.base_time.to_timestamp(at)),
+                        kind: Some(kind),
+                    });
+                }
+            }
+            Event::Panic {
+                message,
+                location,
+                thread,
+                backtrace,
+                spans,
+                at,
+            } => {
+                self.panics.push_back(wire::panics::PanicEvent {
+                    at: Some(self.base_time.to_timestamp(at)),
+                    message,
+                    location,
+                    thread,
+                    backtrace,
+                    spans,
+                });
+                self.unsent_panics += 1;
+
+                if self.panics.len() > Self::PANIC_HISTORY_CAPACITY {
+                    self.panics.pop_front();
+                    self.unsent_panics = self.unsent_panics.min(self.panics.len());
+                }
+            }
+            Event::Spawn {
+                id,
+                metadata,
+                stats,
+                kind,
+                fields,
+                parents,
+                location,
+            } => {
+                let mut task = Task {
+                    id: id.clone(),
+                    is_dirty: AtomicBool::new(true),
+                    metadata,
+                    kind,
+                    fields,
+                    parents,
+                    location,
+                    size: 0,
+                };
+                task.size = task.to_proto(&self.base_time).encoded_len()
+                    + stats.to_proto(&self.base_time).encoded_len();
+
+                self.tasks.insert(id.clone(), task);
+                self.live_tasks.insert(id.clone());
+
+                self.task_stats.insert(id, stats);
+            }
+            Event::Resource {
+                id,
+                metadata,
+                stats,
+                kind,
+                concrete_type,
+                fields,
+                location,
+                parent,
+                is_internal,
+            } => {
+                let mut resource = Resource {
+                    id: id.clone(),
+                    is_dirty: AtomicBool::new(true),
+                    metadata,
+                    kind,
+                    concrete_type,
+                    fields,
+                    location,
+                    parent,
+                    is_internal,
+                    size: 0,
+                };
+                resource.size = resource.to_proto(&self.base_time).encoded_len()
+                    + stats.to_proto(&self.base_time).encoded_len();
+
+                self.resources.insert(id.clone(), resource);
+                self.live_resources.insert(id.clone());
+                self.resource_stats.insert(id, stats);
+            }
+            Event::AsyncOp {
+                id,
+                metadata,
+                stats,
+                source,
+                resource,
+            } => {
+                let mut async_op = AsyncOp {
+                    id: id.clone(),
+                    is_dirty: AtomicBool::new(true),
+                    metadata,
+                    source,
+                    resource,
+                    size: 0,
+                };
+                async_op.size = async_op.to_proto(&self.base_time).encoded_len()
+                    + stats.to_proto(&self.base_time).encoded_len();
+
+                self.async_ops.insert(id.clone(), async_op);
+                self.live_async_ops.insert(id.clone());
+                self.async_op_stats.insert(id, stats);
+            }
+            Event::NewSpan {
+                id,
+                parent,
+                metadata,
+                fields,
+                at,
+            } => {
+                let open_span = OpenSpan {
+                    at: self.base_time.to_timestamp(at),
+                    parent: parent.map(Into::into),
+                    metadata,
+                    fields: Arc::new(fields),
+                    is_unsent: !self.watchers.is_empty(),
+                };
+
+                if open_span.is_unsent {
+                    self.span_events.push(open_span.to_proto(&id));
+                }
+
+                self.open_spans.insert(id, open_span);
+
+                if self.open_spans.len() > Self::MAX_OPEN_SPANS {
+                    self.evict_open_spans();
+                }
+            }
+            Event::SpanRecorded { id, fields, at } => {
+                if let Some(open_span) = self.open_spans.get_mut(&id) {
+                    // log events emitted before keep the values they were emitted with
+                    let span_fields = Arc::make_mut(&mut open_span.fields);
+
+                    for field in &fields {
+                        match span_fields
+                            .iter_mut()
+                            .find(|existing| existing.name == field.name)
+                        {
+                            Some(existing) => *existing = field.clone(),
+                            None => span_fields.push(field.clone()),
+                        }
+                    }
+                }
+
+                self.push_span_event(
+                    at,
+                    wire::spans::span_event::Event::Record(wire::spans::span_event::Record {
+                        span: Some(id.into()),
                         fields,
-                        kind,
-                        handler: Some(handler),
-                    },
+                    }),
                 );
+            }
+            Event::FollowsFrom { id, follows, at } => self.push_span_event(
+                at,
+                wire::spans::span_event::Event::FollowsFrom(
+                    wire::spans::span_event::FollowsFrom {
+                        span: Some(id.into()),
+                        follows: Some(follows.into()),
+                    },
+                ),
+            ),
+            Event::EnterSpan { id, at } => self.push_span_event(
+                at,
+                wire::spans::span_event::Event::EnterSpan(wire::spans::span_event::Enter {
+                    span: Some(id.into()),
+                }),
+            ),
+            Event::ExitSpan { id, at } => self.push_span_event(
+                at,
+                wire::spans::span_event::Event::ExitSpan(wire::spans::span_event::Exit {
+                    span: Some(id.into()),
+                }),
+            ),
+            Event::CloseSpan { id, at } => {
+                self.open_spans.remove(&id);
 
-                self.ipc_request_stats.insert(id, stats);
+                if let Some(tauri_event) = self.tauri_events.get_mut(&id) {
+                    tauri_event.completed_at = Some(at);
+                    self.completed_tauri_events.insert(Completed {
+                        key: id.clone(),
+                        completed_at: at,
+                        size: tauri_event.size,
+                    });
+                }
+
+                self.push_span_event(
+                    at,
+                    wire::spans::span_event::Event::CloseSpan(wire::spans::span_event::Close {
+                        span: Some(id.into()),
+                    }),
+                )
             }
         }
     }
 
+    fn push_span_event(&mut self, at: Instant, event: wire::spans::span_event::Event) {
+        // without watchers there is nobody to send this to, new watchers receive the open spans instead
+        if self.watchers.is_empty() {
+            return;
+        }
+
+        self.span_events.push(wire::spans::SpanEvent {
+            at: Some(self.base_time.to_timestamp(at)),
+            event: Some(event),
+        });
+    }
+
     fn publish(&mut self) {
         let now = Instant::now();
 
-        let new_metadata = if !self.new_metadata.is_empty() {
+        let dropped_metadata = self.shared.dropped_metadata_events.take_pending();
+        let new_metadata = if !self.new_metadata.is_empty() || dropped_metadata > 0 {
+            let metadata = mem::take(&mut self.new_metadata);
+            self.all_metadata.extend(metadata.iter().cloned());
+
             Some(wire::RegisterMetadata {
-                metadata: mem::take(&mut self.new_metadata),
+                metadata,
+                dropped_events: dropped_metadata,
             })
         } else {
             None
         };
 
-        let log_update = if !self.log_events.is_empty() {
+        // log events are filtered for every watcher separately below
+        let log_update = if self.log_history.has_unsent()
+            || self.shared.dropped_log_events.has_pending()
+        {
             Some(self.log_update(Include::UpdateOnly))
         } else {
             None
@@ -232,42 +941,584 @@ impl Aggregator {
 
         let ipc_update = Some(self.ipc_update(Include::UpdateOnly));
 
-        let update = &wire::instrument::Update {
+        let task_update = Some(self.task_update(Include::UpdateOnly));
+
+        let span_update = if !self.span_events.is_empty()
+            || self.shared.dropped_span_events.has_pending()
+        {
+            Some(self.span_update(Include::UpdateOnly))
+        } else {
+            None
+        };
+
+        let events_update = Some(self.events_update(Include::UpdateOnly));
+
+        let window_update = Some(self.window_update(Include::UpdateOnly));
+
+        let metrics_update = if self.unsent_metrics > 0 {
+            Some(self.metrics_update(Include::UpdateOnly))
+        } else {
+            None
+        };
+
+        let panic_update =
+            if self.unsent_panics > 0 || self.shared.dropped_panic_events.has_pending() {
+                Some(self.panic_update(Include::UpdateOnly))
+            } else {
+                None
+            };
+
+        let update = wire::instrument::Update {
             now: Some(self.base_time.to_timestamp(now)),
-            log_update,
+            log_update: None,
             ipc_update,
+            task_update,
+            span_update,
+            events_update,
+            window_update,
+            metrics_update,
+            panic_update,
             new_metadata,
+            skipped_updates: 0,
         };
 
-        self.watchers.retain(|watcher| watcher.update(update));
+        self.watchers.retain_mut(|watcher| {
+            // lagging watchers catch up with the full state below
+            if watcher.skipped > 0 {
+                watcher.skipped += 1;
+                return !watcher.tx.is_closed();
+            }
+
+            let mut update = filter_update(&update, watcher.interests);
+            update.log_update = log_update
+                .as_ref()
+                .filter(|_| watcher.interests.contains(Interests::Trace))
+                .map(|log_update| log_update.to_proto(&watcher.log_filter));
+
+            if watcher.update(update) {
+                true
+            } else if watcher.lossless && !watcher.tx.is_closed() {
+                tracing::debug!("lossless watcher fell behind, skipping updates");
+                watcher.skipped = 1;
+                true
+            } else {
+                false
+            }
+        });
         self.watchers.shrink_to_fit();
+
+        self.catch_up_lagging();
     }
 
-    fn log_update(&mut self, include: Include) -> wire::log::LogUpdate {
-        let new_events = match include {
-            Include::All => self.log_events.clone(),
-            Include::UpdateOnly => mem::take(&mut self.log_events),
-        };
+    /// Sends the full state to the lagging watchers that have room in their buffer again.
+    fn catch_up_lagging(&mut self) {
+        for idx in 0..self.watchers.len() {
+            let watcher = &self.watchers[idx];
+            if watcher.skipped == 0 || watcher.tx.capacity() == 0 {
+                continue;
+            }
 
-        wire::log::LogUpdate {
-            new_events,
-            dropped_events: self.shared.dropped_log_events.swap(0, Ordering::AcqRel) as u64,
+            let (interests, log_filter) = (watcher.interests, watcher.log_filter.clone());
+            let mut update = self.initial_update(interests, &log_filter);
+            update.skipped_updates = self.watchers[idx].skipped;
+
+            if self.watchers[idx].update(update) {
+                self.watchers[idx].skipped = 0;
+            }
         }
     }
 
-    fn ipc_update(&mut self, include: Include) -> wire::ipc::IpcUpdate {
-        wire::ipc::IpcUpdate {
-            new_requests: self.ipc_requests.to_proto_list(include, &self.base_time),
-            stats_update: self.ipc_request_stats.to_proto_map(include, &self.base_time),
-            dropped_events: self.shared.dropped_log_events.swap(0, Ordering::AcqRel) as u64,
+    fn sample_metrics(&mut self) {
+        let Some(sample) = self.sampler.sample(&self.base_time) else {
+            return;
+        };
+
+        self.metrics.push_back(sample);
+        self.unsent_metrics += 1;
+
+        if self.metrics.len() > Self::METRICS_HISTORY_CAPACITY {
+            self.metrics.pop_front();
+            self.unsent_metrics = self.unsent_metrics.min(self.metrics.len());
         }
     }
-}
 
-impl Flush {
-    pub fn trigger(&self) {
-        if self
-            .triggered
+    /// Adds the IPC requests that completed since the last call to the stats of their command,
+    /// drops the command stats' durations that left the window, and queues them and the tasks, resources and async operations that were dropped since
+    /// the last call for eviction.
+    fn aggregate_completed(&mut self) {
+        let now = Instant::now();
+        for stats in self.command_stats.values_mut() {
+            stats.rotate(now);
+        }
+
+        self.incomplete_ipc_requests.retain(|id| {
+            let (Some(request), Some(stats)) =
+                (self.ipc_requests.get(id), self.ipc_request_stats.get(id))
+            else {
+                return false;
+            };
+
+            let Some(completed_at) = stats.completed_at() else {
+                return true;
+            };
+
+            let command_duration_max = self.command_duration_max;
+            self.command_stats
+                .entry(request.cmd.clone())
+                .or_insert_with(|| stats::CommandStats::new(command_duration_max, completed_at))
+                .add(stats, completed_at);
+
+            let payloads = self.ipc_request_payloads.get(id);
+            self.completed_ipc_requests.insert(Completed {
+                key: id.clone(),
+                completed_at,
+                size: request.size + payloads.map_or(0, |payloads| payloads.size),
+            });
+
+            false
+        });
+
+        self.live_tasks.retain(|id| {
+            let (Some(task), Some(stats)) = (self.tasks.get(id), self.task_stats.get(id)) else {
+                return false;
+            };
+            let Some(dropped_at) = stats.dropped_at() else {
+                return true;
+            };
+
+            self.completed_tasks.insert(Completed {
+                key: id.clone(),
+                completed_at: dropped_at,
+                size: task.size,
+            });
+            false
+        });
+
+        self.live_resources.retain(|id| {
+            let (Some(resource), Some(stats)) =
+                (self.resources.get(id), self.resource_stats.get(id))
+            else {
+                return false;
+            };
+            let Some(dropped_at) = stats.dropped_at() else {
+                return true;
+            };
+
+            self.completed_resources.insert(Completed {
+                key: id.clone(),
+                completed_at: dropped_at,
+                size: resource.size,
+            });
+            false
+        });
+
+        self.live_async_ops.retain(|id| {
+            let (Some(async_op), Some(stats)) =
+                (self.async_ops.get(id), self.async_op_stats.get(id))
+            else {
+                return false;
+            };
+            let Some(dropped_at) = stats.dropped_at() else {
+                return true;
+            };
+
+            self.completed_async_ops.insert(Completed {
+                key: id.clone(),
+                completed_at: dropped_at,
+                size: async_op.size,
+            });
+            false
+        });
+    }
+
+    /// Evicts completed IPC requests and Tauri events, dropped tasks, resources and async operations,
+    /// closed windows and old log events according to the retention policy.
+    ///
+    /// Entries that have not been sent to the current watchers yet are kept until the next publish.
+    /// Span IDs and window labels may be reused, so only entries that are still completed are removed.
+    fn cleanup_closed(&mut self) {
+        let now = Instant::now();
+        let has_watchers = !self.watchers.is_empty();
+
+        self.log_history.evict(now, has_watchers);
+
+        let evicted = self
+            .retention
+            .evict(&mut self.completed_ipc_requests, now, |id| {
+                has_watchers
+                    && (self.ipc_requests.get(id).is_some_and(Unsent::is_unsent)
+                        || self.ipc_request_stats.get(id).is_some_and(Unsent::is_unsent)
+                        || self.ipc_request_payloads.get(id).is_some_and(Unsent::is_unsent))
+            });
+        for id in evicted {
+            if self
+                .ipc_request_stats
+                .get(&id)
+                .is_some_and(|stats| stats.completed_at().is_some())
+            {
+                self.ipc_requests.remove(&id);
+                self.ipc_request_stats.remove(&id);
+                self.ipc_request_payloads.remove(&id);
+            }
+        }
+
+        let evicted = self.retention.evict(&mut self.completed_tasks, now, |id| {
+            has_watchers
+                && (self.tasks.get(id).is_some_and(Unsent::is_unsent)
+                    || self.task_stats.get(id).is_some_and(Unsent::is_unsent))
+        });
+        for id in evicted {
+            if self
+                .task_stats
+                .get(&id)
+                .is_some_and(|stats| stats.dropped_at().is_some())
+            {
+                self.tasks.remove(&id);
+                self.task_stats.remove(&id);
+            }
+        }
+
+        let evicted = self.retention.evict(&mut self.completed_resources, now, |id| {
+            has_watchers
+                && (self.resources.get(id).is_some_and(Unsent::is_unsent)
+                    || self.resource_stats.get(id).is_some_and(Unsent::is_unsent))
+        });
+        for id in evicted {
+            if self
+                .resource_stats
+                .get(&id)
+                .is_some_and(|stats| stats.dropped_at().is_some())
+            {
+                self.resources.remove(&id);
+                self.resource_stats.remove(&id);
+            }
+        }
+
+        let evicted = self.retention.evict(&mut self.completed_async_ops, now, |id| {
+            has_watchers
+                && (self.async_ops.get(id).is_some_and(Unsent::is_unsent)
+                    || self.async_op_stats.get(id).is_some_and(Unsent::is_unsent))
+        });
+        for id in evicted {
+            if self
+                .async_op_stats
+                .get(&id)
+                .is_some_and(|stats| stats.dropped_at().is_some())
+            {
+                self.async_ops.remove(&id);
+                self.async_op_stats.remove(&id);
+            }
+        }
+
+        let evicted = self
+            .retention
+            .evict(&mut self.completed_tauri_events, now, |id| {
+                has_watchers
+                    && self.tauri_events.get(id).is_some_and(|tauri_event| {
+                        tauri_event.is_unsent()
+                            || tauri_event.sent_deliveries < tauri_event.deliveries.len()
+                    })
+            });
+        for id in evicted {
+            if self
+                .tauri_events
+                .get(&id)
+                .is_some_and(|tauri_event| tauri_event.completed_at.is_some())
+            {
+                self.tauri_events.remove(&id);
+            }
+        }
+
+        let evicted = self.retention.evict(&mut self.closed_windows, now, |label| {
+            has_watchers && self.windows.get(label).is_some_and(|window| window.is_dirty)
+        });
+        for label in evicted {
+            if self
+                .windows
+                .get(&label)
+                .is_some_and(|window| window.closed_at.is_some())
+            {
+                self.windows.remove(&label);
+            }
+        }
+    }
+
+    fn log_update(&mut self, include: Include) -> LogUpdate {
+        let entries = match include {
+            // unsent events will be part of the next published update
+            Include::All => self.log_history.sent(),
+            Include::UpdateOnly => self.log_history.take_unsent(),
+        };
+
+        LogUpdate {
+            entries,
+            dropped_events: take_dropped(&self.shared.dropped_log_events, include),
+        }
+    }
+
+    fn ipc_update(&mut self, include: Include) -> wire::ipc::IpcUpdate {
+        wire::ipc::IpcUpdate {
+            new_requests: self.ipc_requests.to_proto_list(include, &self.base_time),
+            stats_update: self.ipc_request_stats.to_proto_map(include, &self.base_time),
+            payloads_update: self
+                .ipc_request_payloads
+                .to_proto_map(include, &self.base_time),
+            command_stats_update: self
+                .command_stats
+                .iter()
+                .filter(|(_, stats)| match include {
+                    Include::All => true,
+                    Include::UpdateOnly => stats.take_unsent(),
+                })
+                .map(|(cmd, stats)| (cmd.clone(), stats.to_proto(&self.base_time)))
+                .collect(),
+            dropped_events: take_dropped(&self.shared.dropped_ipc_events, include),
+        }
+    }
+
+    fn task_update(&mut self, include: Include) -> wire::tasks::TaskUpdate {
+        wire::tasks::TaskUpdate {
+            new_tasks: self.tasks.to_proto_list(include, &self.base_time),
+            stats_update: self.task_stats.to_proto_map(include, &self.base_time),
+            dropped_events: take_dropped(&self.shared.dropped_task_events, include),
+            new_resources: self.resources.to_proto_list(include, &self.base_time),
+            resource_stats_update: self.resource_stats.to_proto_map(include, &self.base_time),
+            new_async_ops: self.async_ops.to_proto_list(include, &self.base_time),
+            async_op_stats_update: self.async_op_stats.to_proto_map(include, &self.base_time),
+        }
+    }
+
+    fn events_update(&mut self, include: Include) -> wire::events::EventsUpdate {
+        let new_events = self.tauri_events.to_proto_list(include, &self.base_time);
+
+        let mut new_deliveries = Vec::new();
+        for (_, tauri_event) in self.tauri_events.all_mut() {
+            let start = match include {
+                Include::All => 0,
+                Include::UpdateOnly => mem::replace(
+                    &mut tauri_event.sent_deliveries,
+                    tauri_event.deliveries.len(),
+                ),
+            };
+
+            new_deliveries.extend_from_slice(&tauri_event.deliveries[start..]);
+        }
+        new_deliveries.sort_by_key(|delivery| {
+            delivery
+                .delivered_at
+                .as_ref()
+                .map(|at| (at.seconds, at.nanos))
+        });
+
+        wire::events::EventsUpdate {
+            new_events,
+            new_deliveries,
+            dropped_events: take_dropped(&self.shared.dropped_tauri_events, include),
+        }
+    }
+
+    fn window_update(&mut self, include: Include) -> wire::windows::WindowUpdate {
+        let mut windows: Vec<_> = self
+            .windows
+            .values_mut()
+            .filter_map(|window| {
+                let changed = match include {
+                    Include::All => true,
+                    Include::UpdateOnly => mem::take(&mut window.is_dirty),
+                };
+
+                changed.then_some(&*window)
+            })
+            .collect();
+        windows.sort_by_key(|window| window.created_at);
+
+        let new_events = match include {
+            // events are only sent live, the windows already reflect them
+            Include::All => Vec::new(),
+            Include::UpdateOnly => mem::take(&mut self.window_events),
+        };
+
+        wire::windows::WindowUpdate {
+            windows: windows
+                .into_iter()
+                .map(|window| window.to_proto(&self.base_time))
+                .collect(),
+            new_events,
+            dropped_events: take_dropped(&self.shared.dropped_window_events, include),
+        }
+    }
+
+    fn metrics_update(&mut self, include: Include) -> wire::metrics::MetricsUpdate {
+        let sent = self.metrics.len() - self.unsent_metrics;
+
+        let samples = match include {
+            // unsent samples will be part of the next published update
+            Include::All => self.metrics.range(..sent).cloned().collect(),
+            Include::UpdateOnly => {
+                self.unsent_metrics = 0;
+                self.metrics.range(sent..).cloned().collect()
+            }
+        };
+
+        wire::metrics::MetricsUpdate { samples }
+    }
+
+    fn panic_update(&mut self, include: Include) -> wire::panics::PanicUpdate {
+        let sent = self.panics.len() - self.unsent_panics;
+
+        let new_panics = match include {
+            // unsent panics will be part of the next published update
+            Include::All => self.panics.range(..sent).cloned().collect(),
+            Include::UpdateOnly => {
+                self.unsent_panics = 0;
+                self.panics.range(sent..).cloned().collect()
+            }
+        };
+
+        wire::panics::PanicUpdate {
+            new_panics,
+            dropped_events: take_dropped(&self.shared.dropped_panic_events, include),
+        }
+    }
+
+    /// Returns all windows that have not been closed yet, in the order they were created.
+    fn open_windows(&self) -> Vec<wire::windows::Window> {
+        let mut windows: Vec<_> = self
+            .windows
+            .values()
+            .filter(|window| window.closed_at.is_none())
+            .collect();
+        windows.sort_by_key(|window| window.created_at);
+
+        windows
+            .into_iter()
+            .map(|window| window.to_proto(&self.base_time))
+            .collect()
+    }
+
+    /// Returns the names of all commands with completed or retained requests.
+    fn invoked_commands(&self) -> Vec<String> {
+        let commands: HashSet<_> = self
+            .command_stats
+            .keys()
+            .chain(self.ipc_requests.all().map(|(_, request)| &request.cmd))
+            .cloned()
+            .collect();
+
+        commands.into_iter().collect()
+    }
+
+    /// Evicts the oldest quarter of the open spans.
+    fn evict_open_spans(&mut self) {
+        let mut open_spans: Vec<_> = self
+            .open_spans
+            .iter()
+            .map(|(id, open_span)| ((open_span.at.seconds, open_span.at.nanos), id.clone()))
+            .collect();
+
+        let evicted = open_spans.len() / 4;
+        open_spans.select_nth_unstable_by_key(evicted, |(at, _)| *at);
+
+        for (_, id) in open_spans.drain(..evicted) {
+            self.open_spans.remove(&id);
+        }
+    }
+
+    fn span_update(&mut self, include: Include) -> wire::spans::SpanUpdate {
+        let span_events = match include {
+            Include::All => {
+                // spans still buffered in `span_events` will be part of the next published update
+                let mut open_spans: Vec<_> = self
+                    .open_spans
+                    .iter()
+                    .filter(|(_, open_span)| !open_span.is_unsent)
+                    .collect();
+                // make sure parents are sent before their children
+                open_spans.sort_by_key(|(_, open_span)| (open_span.at.seconds, open_span.at.nanos));
+
+                open_spans
+                    .into_iter()
+                    .map(|(id, open_span)| open_span.to_proto(id))
+                    .collect()
+            }
+            Include::UpdateOnly => {
+                for open_span in self.open_spans.values_mut() {
+                    open_span.is_unsent = false;
+                }
+
+                mem::take(&mut self.span_events)
+            }
+        };
+
+        wire::spans::SpanUpdate {
+            span_events,
+            dropped_events: take_dropped(&self.shared.dropped_span_events, include),
+        }
+    }
+}
+
+/// Returns the number of dropped events to report in an update.
+///
+/// Drops are only reported (and reset) by published updates, so that sending the full
+/// state to a single watcher doesn't hide them from everybody else.
+fn take_dropped(dropped: &DroppedEvents, include: Include) -> u64 {
+    match include {
+        Include::All => 0,
+        Include::UpdateOnly => dropped.take_pending(),
+    }
+}
+
+/// Returns a copy of `update` containing only the parts the watcher is interested in.
+fn filter_update(
+    update: &wire::instrument::Update,
+    interests: Interests,
+) -> wire::instrument::Update {
+    wire::instrument::Update {
+        now: update.now.clone(),
+        new_metadata: update
+            .new_metadata
+            .clone()
+            .filter(|_| interests.contains(Interests::Metadata)),
+        log_update: update
+            .log_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Trace)),
+        ipc_update: update
+            .ipc_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Ipc)),
+        task_update: update
+            .task_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Tasks)),
+        span_update: update
+            .span_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Spans)),
+        events_update: update
+            .events_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Events)),
+        window_update: update
+            .window_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Windows)),
+        metrics_update: update
+            .metrics_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Metrics)),
+        panic_update: update
+            .panic_update
+            .clone()
+            .filter(|_| interests.contains(Interests::Panics)),
+        skipped_updates: update.skipped_updates,
+    }
+}
+
+impl Flush {
+    pub fn trigger(&self) {
+        if self
+            .triggered
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
             .is_ok()
         {
@@ -285,6 +1536,39 @@ impl Flush {
     }
 }
 
+impl LogUpdate {
+    fn to_proto(&self, log_filter: &LogFilter) -> wire::log::LogUpdate {
+        let new_events = self
+            .entries
+            .iter()
+            .filter(|entry| log_filter.enabled(entry.metadata, &entry.event.fields, &entry.scope))
+            .map(|entry| entry.event.clone())
+            .collect();
+
+        wire::log::LogUpdate {
+            new_events,
+            dropped_events: self.dropped_events,
+        }
+    }
+}
+
+impl OpenSpan {
+    /// Returns the event announcing this span to clients, with all values recorded so far.
+    fn to_proto(&self, id: &tracing_core::span::Id) -> wire::spans::SpanEvent {
+        wire::spans::SpanEvent {
+            at: Some(self.at.clone()),
+            event: Some(wire::spans::span_event::Event::NewSpan(
+                wire::spans::span_event::Span {
+                    id: Some(id.clone().into()),
+                    parent: self.parent.clone(),
+                    metadata_id: Some(self.metadata.into()),
+                    fields: self.fields.as_ref().clone(),
+                },
+            )),
+        }
+    }
+}
+
 impl Unsent for IPCRequest {
     fn take_unsent(&self) -> bool {
         self.is_dirty.swap(false, Ordering::AcqRel)
@@ -305,7 +1589,338 @@ impl ToProto for IPCRequest {
             metadata: Some(self.metadata.into()),
             fields: self.fields.clone(),
             kind: self.kind as i32,
-            handler: self.handler.clone()
+            handler: self.handler.clone(),
+            window: self.window.clone(),
+        }
+    }
+}
+
+impl Unsent for IPCPayloads {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for IPCPayloads {
+    type Output = wire::ipc::Payloads;
+
+    fn to_proto(&self, _base_time: &TimeAnchor) -> Self::Output {
+        wire::ipc::Payloads {
+            args: self.args.clone(),
+            response: self.response.clone(),
+        }
+    }
+}
+
+impl Unsent for Task {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for Task {
+    type Output = wire::tasks::Task;
+
+    fn to_proto(&self, _base_time: &TimeAnchor) -> Self::Output {
+        wire::tasks::Task {
+            id: Some(self.id.clone().into()),
+            metadata: Some(self.metadata.into()),
+            kind: self.kind as i32,
+            fields: self.fields.clone(),
+            parents: self.parents.clone(),
+            location: Some(self.location.clone()),
+        }
+    }
+}
+
+impl Unsent for Resource {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for Resource {
+    type Output = wire::tasks::Resource;
+
+    fn to_proto(&self, _base_time: &TimeAnchor) -> Self::Output {
+        wire::tasks::Resource {
+            id: Some(self.id.clone().into()),
+            metadata: Some(self.metadata.into()),
+            kind: self.kind.clone(),
+            concrete_type: self.concrete_type.clone(),
+            location: Some(self.location.clone()),
+            parent_resource_id: self.parent.clone().map(Into::into),
+            is_internal: self.is_internal,
+            fields: self.fields.clone(),
+        }
+    }
+}
+
+impl Unsent for AsyncOp {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for AsyncOp {
+    type Output = wire::tasks::AsyncOp;
+
+    fn to_proto(&self, _base_time: &TimeAnchor) -> Self::Output {
+        wire::tasks::AsyncOp {
+            id: Some(self.id.clone().into()),
+            metadata: Some(self.metadata.into()),
+            source: self.source.clone(),
+            resource_id: Some(self.resource.clone().into()),
+        }
+    }
+}
+
+impl Unsent for TauriEvent {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for TauriEvent {
+    type Output = wire::events::Event;
+
+    fn to_proto(&self, base_time: &TimeAnchor) -> Self::Output {
+        wire::events::Event {
+            id: Some(self.id.clone().into()),
+            name: self.name.clone(),
+            source_window: self.source_window.clone(),
+            target_window: self.target_window.clone(),
+            payload_len: self.payload_len,
+            emitted_at: Some(base_time.to_timestamp(self.emitted_at)),
+            metadata: Some(self.metadata.into()),
+            fields: self.fields.clone(),
+        }
+    }
+}
+
+impl ToProto for Window {
+    type Output = wire::windows::Window;
+
+    fn to_proto(&self, base_time: &TimeAnchor) -> Self::Output {
+        wire::windows::Window {
+            label: self.label.clone(),
+            url: self.url.clone(),
+            focused: self.focused,
+            size: self.size.clone(),
+            created_at: Some(base_time.to_timestamp(self.created_at)),
+            closed_at: self.closed_at.map(|at| base_time.to_timestamp(at)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_core::{callsite::Callsite, metadata::Kind, Interest, Level, Metadata};
+
+    struct TestCallsite;
+
+    static CALLSITE: TestCallsite = TestCallsite;
+
+    static METADATA: Metadata<'static> = tracing_core::metadata! {
+        name: "test",
+        target: "subscriber::aggregator::tests",
+        level: Level::INFO,
+        fields: &["message"],
+        callsite: &CALLSITE,
+        kind: Kind::EVENT,
+    };
+
+    impl Callsite for TestCallsite {
+        fn set_interest(&self, _: Interest) {}
+
+        fn metadata(&self) -> &Metadata<'_> {
+            &METADATA
+        }
+    }
+
+    type Updates = mpsc::Receiver<Result<wire::instrument::Update, tonic::Status>>;
+
+    /// Spawns an aggregator publishing every 10 milliseconds.
+    fn spawn_aggregator() -> (mpsc::Sender<Event>, mpsc::Sender<Command>) {
+        spawn_aggregator_with(Arc::new(Shared::default()))
+    }
+
+    fn spawn_aggregator_with(shared: Arc<Shared>) -> (mpsc::Sender<Event>, mpsc::Sender<Command>) {
+        let (event_tx, events) = mpsc::channel(1024);
+        let (command_tx, commands) = mpsc::channel(16);
+
+        let aggregator = Aggregator::new(
+            shared,
+            events,
+            commands,
+            Duration::from_millis(10),
+            Aggregator::DEFAULT_METRICS_INTERVAL,
+            Retention::default(),
+            LogHistory::new(
+                LogHistory::DEFAULT_CAPACITY,
+                LogHistory::DEFAULT_MAX_AGE,
+                Retention::DEFAULT_MAX_BYTES,
+            ),
+        );
+        tokio::spawn(aggregator.run());
+
+        (event_tx, command_tx)
+    }
+
+    async fn watch(commands: &mpsc::Sender<Command>) -> Updates {
+        watch_with(commands, 1024, false).await
+    }
+
+    async fn watch_with(
+        commands: &mpsc::Sender<Command>,
+        capacity: usize,
+        lossless: bool,
+    ) -> Updates {
+        let (tx, rx) = mpsc::channel(capacity);
+
+        commands
+            .send(Command::Instrument(Watch {
+                tx,
+                interests: Interests::all(),
+                log_filter: LogFilter::default(),
+                peer: None,
+                lossless,
+                skipped: 0,
+            }))
+            .await
+            .unwrap();
+
+        rx
+    }
+
+    fn log_event() -> Event {
+        Event::LogEvent {
+            metadata: &METADATA,
+            fields: Vec::new(),
+            at: Instant::now(),
+            parents: Vec::new(),
+            ipc_request: None,
+        }
+    }
+
+    /// Waits for a few publishes.
+    async fn publish() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    /// Returns how often the test callsite was registered in the updates received so far.
+    fn registrations(updates: &mut Updates) -> usize {
+        let id = wire::MetaId::from(&METADATA).id;
+        let mut registrations = 0;
+
+        while let Ok(update) = updates.try_recv() {
+            let update = update.unwrap();
+            registrations += update
+                .new_metadata
+                .iter()
+                .flat_map(|new_metadata| &new_metadata.metadata)
+                .filter(|metadata| metadata.id.as_ref().map(|id| id.id) == Some(id))
+                .count();
+        }
+
+        registrations
+    }
+
+    #[tokio::test]
+    async fn callsites_are_registered_once_per_client() {
+        let (events, commands) = spawn_aggregator();
+
+        let mut first = watch(&commands).await;
+        for _ in 0..3 {
+            events.send(Event::Metadata(&METADATA)).await.unwrap();
+            events.send(log_event()).await.unwrap();
+        }
+        publish().await;
+
+        let mut second = watch(&commands).await;
+        for _ in 0..3 {
+            events.send(Event::Metadata(&METADATA)).await.unwrap();
+            events.send(log_event()).await.unwrap();
+        }
+        publish().await;
+
+        assert_eq!(registrations(&mut first), 1);
+        assert_eq!(registrations(&mut second), 1);
+    }
+
+    #[tokio::test]
+    async fn dropped_callsites_are_registered_with_their_first_event() {
+        let (events, commands) = spawn_aggregator();
+
+        let mut updates = watch(&commands).await;
+        for _ in 0..3 {
+            events.send(log_event()).await.unwrap();
+        }
+        publish().await;
+
+        assert_eq!(registrations(&mut updates), 1);
+    }
+
+    #[tokio::test]
+    async fn lossless_watchers_catch_up_with_the_full_state() {
+        let (events, commands) = spawn_aggregator();
+
+        // the initial update fills the buffer, so the following updates are skipped
+        let mut updates = watch_with(&commands, 1, true).await;
+        for _ in 0..3 {
+            events.send(log_event()).await.unwrap();
+        }
+        publish().await;
+
+        assert!(updates.try_recv().is_ok());
+        publish().await;
+
+        let update = updates.try_recv().unwrap().unwrap();
+        assert!(update.skipped_updates > 0);
+        assert_eq!(update.log_update.unwrap().new_events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn events_dropped_before_the_first_client_are_reported() {
+        let shared = Arc::new(Shared::default());
+        let (events, commands) = spawn_aggregator_with(shared.clone());
+
+        shared.dropped_log_events.record(2);
+        shared.dropped_span_events.record(3);
+        events.send(log_event()).await.unwrap();
+        publish().await;
+
+        let mut updates = watch(&commands).await;
+        publish().await;
+
+        let (mut dropped_logs, mut dropped_spans) = (0, 0);
+        while let Ok(update) = updates.try_recv() {
+            let update = update.unwrap();
+            dropped_logs += update.log_update.map_or(0, |u| u.dropped_events);
+            dropped_spans += update.span_update.map_or(0, |u| u.dropped_events);
         }
+        assert_eq!(dropped_logs, 2);
+        assert_eq!(dropped_spans, 3);
     }
 }
@@ -0,0 +1,281 @@
+use crate::util::{matches_pattern, TimeAnchor};
+use std::{collections::BTreeMap, time::Instant};
+
+/// Replaces the values of redacted command line flags and configuration keys.
+const REDACTED: &str = "<redacted>";
+
+/// Static information about the instrumented app, collected when the devtools are spawned.
+pub(crate) struct AppInfo {
+    package_info: tauri::PackageInfo,
+    /// The effective configuration, with the values of redacted keys replaced.
+    config: serde_json::Value,
+    started_at: Instant,
+    base_time: TimeAnchor,
+    /// Environment variables, command line flags and configuration keys whose name matches one of
+    /// these patterns are redacted.
+    redact: Vec<String>,
+}
+
+impl AppInfo {
+    pub(crate) fn new<A: tauri::Assets>(ctx: &tauri::Context<A>, redact: Vec<String>) -> Self {
+        let mut config = serde_json::to_value(ctx.config()).unwrap_or_default();
+        redact_config(&mut config, &|name| {
+            redact.iter().any(|pattern| matches_pattern(pattern, name))
+        });
+
+        Self {
+            package_info: ctx.package_info().clone(),
+            config,
+            started_at: Instant::now(),
+            base_time: TimeAnchor::new(),
+            redact,
+        }
+    }
+
+    pub(crate) fn package_info(&self) -> wire::application::PackageInfo {
+        wire::application::PackageInfo {
+            name: self.package_info.name.clone(),
+            version: self.package_info.version.to_string(),
+            authors: self.package_info.authors.to_string(),
+            description: self.package_info.description.to_string(),
+        }
+    }
+
+    pub(crate) fn config(&self) -> wire::application::Config {
+        // Tauri 1 keeps the allowlist in the `tauri` section of the config
+        let allowlist = self
+            .config
+            .pointer("/tauri/allowlist")
+            .map(ToString::to_string)
+            .unwrap_or_default();
+
+        wire::application::Config {
+            config: self.config.to_string(),
+            allowlist,
+        }
+    }
+
+    /// Groups the invoked commands by plugin, plugin commands are invoked as `plugin:<name>|<command>`.
+    ///
+    /// Tauri doesn't expose the plugins registered in code, so plugins without a configuration
+    /// entry are missing until one of their commands is invoked.
+    pub(crate) fn plugins(&self, invoked_commands: Vec<String>) -> wire::application::Plugins {
+        let mut plugins: BTreeMap<String, wire::application::Plugin> = BTreeMap::new();
+
+        if let Some(configured) = self.config.get("plugins").and_then(|p| p.as_object()) {
+            for name in configured.keys() {
+                plugins.insert(
+                    name.clone(),
+                    wire::application::Plugin {
+                        name: name.clone(),
+                        configured: true,
+                        commands: Vec::new(),
+                    },
+                );
+            }
+        }
+
+        let mut commands = Vec::new();
+        for cmd in invoked_commands {
+            match cmd
+                .strip_prefix("plugin:")
+                .and_then(|cmd| cmd.split_once('|'))
+            {
+                Some((plugin, cmd)) => plugins
+                    .entry(plugin.to_string())
+                    .or_insert_with(|| wire::application::Plugin {
+                        name: plugin.to_string(),
+                        configured: false,
+                        commands: Vec::new(),
+                    })
+                    .commands
+                    .push(cmd.to_string()),
+                None => commands.push(cmd),
+            }
+        }
+
+        let mut plugins: Vec<_> = plugins.into_values().collect();
+        for plugin in &mut plugins {
+            plugin.commands.sort();
+        }
+        commands.sort();
+
+        wire::application::Plugins { plugins, commands }
+    }
+
+    pub(crate) fn versions(&self) -> wire::application::Versions {
+        wire::application::Versions {
+            tauri: tauri::VERSION.to_string(),
+            rust: env!("DEVTOOLS_RUSTC_VERSION").to_string(),
+            devtools: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    pub(crate) fn process_info(&self) -> wire::application::ProcessInfo {
+        let is_redacted = |name: &str| {
+            self.redact
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+        };
+
+        let mut env: Vec<_> = std::env::vars_os()
+            .map(|(name, value)| {
+                let name = name.to_string_lossy().to_string();
+                let redacted = is_redacted(&name);
+
+                wire::application::EnvVar {
+                    value: if redacted {
+                        String::new()
+                    } else {
+                        value.to_string_lossy().to_string()
+                    },
+                    name,
+                    redacted,
+                }
+            })
+            .collect();
+        env.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let args = redact_args(
+            std::env::args_os().map(|arg| arg.to_string_lossy().to_string()),
+            is_redacted,
+        );
+
+        wire::application::ProcessInfo {
+            pid: std::process::id(),
+            started_at: Some(self.base_time.to_timestamp(self.started_at)),
+            uptime: self.started_at.elapsed().try_into().ok(),
+            args,
+            env,
+        }
+    }
+}
+
+/// Replaces the values of object keys whose name is redacted, at any depth of the configuration.
+fn redact_config(value: &mut serde_json::Value, is_redacted: &impl Fn(&str) -> bool) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                if is_redacted(key) {
+                    *value = REDACTED.into();
+                } else {
+                    redact_config(value, is_redacted);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                redact_config(value, is_redacted);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces the values of flags whose name is redacted, given as either `--name=value` or `--name value`.
+fn redact_args(
+    args: impl Iterator<Item = String>,
+    is_redacted: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    let mut redacted_args = Vec::new();
+    let mut redact_next = false;
+
+    for arg in args {
+        if redact_next && !arg.starts_with('-') {
+            redact_next = false;
+            redacted_args.push(REDACTED.to_string());
+            continue;
+        }
+        redact_next = false;
+
+        let Some(flag) = arg.strip_prefix('-').map(|flag| flag.trim_start_matches('-')) else {
+            redacted_args.push(arg);
+            continue;
+        };
+
+        match flag.split_once('=') {
+            Some((name, _)) if is_redacted(name) => {
+                let prefix_len = arg.len() - flag.len();
+                redacted_args.push(format!("{}{name}={REDACTED}", &arg[..prefix_len]));
+            }
+            Some(_) => redacted_args.push(arg),
+            None => {
+                redact_next = is_redacted(flag);
+                redacted_args.push(arg);
+            }
+        }
+    }
+
+    redacted_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(args: &[&str]) -> Vec<String> {
+        redact_args(args.iter().map(ToString::to_string), |name| {
+            matches_pattern("*token*", name)
+        })
+    }
+
+    #[test]
+    fn redacts_flag_values() {
+        assert_eq!(
+            redact(&[
+                "app",
+                "--api-token=abc",
+                "--api-token",
+                "abc",
+                "-token",
+                "abc"
+            ]),
+            [
+                "app",
+                "--api-token=<redacted>",
+                "--api-token",
+                "<redacted>",
+                "-token",
+                "<redacted>"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_other_args() {
+        assert_eq!(
+            redact(&["app", "--verbose", "file", "--name=token", "token"]),
+            ["app", "--verbose", "file", "--name=token", "token"]
+        );
+    }
+
+    #[test]
+    fn doesnt_redact_following_flags() {
+        assert_eq!(
+            redact(&["app", "--token", "--verbose", "file"]),
+            ["app", "--token", "--verbose", "file"]
+        );
+        assert_eq!(redact(&["app", "--token"]), ["app", "--token"]);
+    }
+
+    #[test]
+    fn redacts_nested_config_keys() {
+        let mut config = serde_json::json!({
+            "build": { "distDir": "../dist" },
+            "plugins": { "updater": { "pubkey": "abc", "endpoints": ["https://example.com"] } },
+            "windows": [{ "label": "main", "userToken": "abc" }],
+        });
+        redact_config(&mut config, &|name| {
+            matches_pattern("*key*", name) || matches_pattern("*token*", name)
+        });
+
+        assert_eq!(
+            config,
+            serde_json::json!({
+                "build": { "distDir": "../dist" },
+                "plugins": { "updater": { "pubkey": REDACTED, "endpoints": ["https://example.com"] } },
+                "windows": [{ "label": "main", "userToken": REDACTED }],
+            })
+        );
+    }
+}
@@ -0,0 +1,655 @@
+use crate::{
+    aggregator::Aggregator, app_info::AppInfo, filters::Filters, layer::Layer,
+    log_history::LogHistory, otlp::Exporter, panics, payloads::{PayloadCapture, RedactHook},
+    recording::Recorder, retention::Retention, server::Server, util::spawn_named,
+    zeroconf::Zeroconf, Shared,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use tokio::{runtime, sync::mpsc};
+use tracing_core::Subscriber;
+use tracing_subscriber::{filter, prelude::*, registry::LookupSpan, reload};
+
+const FILTER_ENV_VAR: &str = "RUST_LOG";
+
+/// Environment variables and command line flags that likely contain secrets.
+const ENV_REDACT_PATTERNS: &[&str] = &[
+    "*token*",
+    "*secret*",
+    "*password*",
+    "*passwd*",
+    "*key*",
+    "*auth*",
+    "*credential*",
+];
+
+/// Builder for configuring the devtools subscriber.
+///
+/// The defaults match what [`init`](crate::init) uses, so only the knobs that
+/// need changing have to be set.
+///
+/// ```ignore
+/// let layer = subscriber::Builder::default()
+///     .server_addr(([127, 0, 0, 1], 6670))
+///     .publish_interval(Duration::from_millis(100))
+///     .spawn(&context);
+///
+/// tracing_subscriber::registry().with(layer).init();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Builder {
+    /// The maximum capacity for the channel of events from the layer to the aggregator.
+    event_buffer_capacity: usize,
+
+    /// The maximum capacity of updates to buffer for each subscribed client.
+    client_buffer_capacity: usize,
+
+    /// The interval between publishing updates to clients.
+    publish_interval: Duration,
+
+    /// The interval between sampling process metrics.
+    metrics_interval: Duration,
+
+    /// How much data about completed IPC requests and tasks is kept.
+    retention: Retention,
+
+    /// The maximum number of log events replayed to new clients.
+    log_history_capacity: usize,
+
+    /// How long log events are replayed to new clients.
+    log_history_max_age: Duration,
+
+    /// The address the gRPC server will listen on.
+    server_addr: SocketAddr,
+
+    /// The maximum value for the busy time histograms.
+    poll_duration_max: Duration,
+
+    /// The maximum value for the waiting time histograms.
+    scheduled_duration_max: Duration,
+
+    /// The maximum value for the duration histograms of the command stats.
+    command_duration_max: Duration,
+
+    /// The environment variable the fmt layer filter is read from.
+    filter_env_var: String,
+
+    /// Explicit filter for the fmt layer, takes precedence over `filter_env_var`.
+    fmt_filter: Option<filter::Targets>,
+
+    /// The maximum size of captured IPC arguments and responses, `None` if they aren't captured.
+    ipc_payload_max_bytes: Option<usize>,
+
+    /// Patterns for IPC argument names whose values are never captured.
+    ipc_redact_patterns: Vec<String>,
+
+    /// Decides whether a captured IPC argument or response is redacted, on top of the patterns.
+    ipc_redact_hook: Option<RedactHook>,
+
+    /// Patterns for environment variables, command line flags and configuration keys whose values
+    /// are never sent.
+    env_redact_patterns: Vec<String>,
+
+    /// Whether a panic hook is installed to report panics to clients.
+    capture_panics: bool,
+
+    /// The file all published updates are recorded to, `None` if the session isn't recorded.
+    recording_path: Option<PathBuf>,
+
+    /// The maximum size of a recording file before the recording continues in a new file.
+    recording_max_file_bytes: u64,
+
+    /// The maximum number of recording files, after which the recording stops.
+    recording_max_files: u32,
+
+    /// The OTLP collector spans and log events are exported to, `None` if nothing is exported.
+    otlp_endpoint: Option<String>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            event_buffer_capacity: Layer::DEFAULT_EVENT_BUFFER_CAPACITY,
+            client_buffer_capacity: Server::DEFAULT_CLIENT_BUFFER_CAPACITY,
+            publish_interval: Aggregator::DEFAULT_PUBLISH_INTERVAL,
+            metrics_interval: Aggregator::DEFAULT_METRICS_INTERVAL,
+            retention: Retention::default(),
+            log_history_capacity: LogHistory::DEFAULT_CAPACITY,
+            log_history_max_age: LogHistory::DEFAULT_MAX_AGE,
+            server_addr: SocketAddr::new(Server::DEFAULT_IP, Server::DEFAULT_PORT),
+            poll_duration_max: Layer::DEFAULT_POLL_DURATION_MAX,
+            scheduled_duration_max: Layer::DEFAULT_SCHEDULED_DURATION_MAX,
+            command_duration_max: Aggregator::DEFAULT_COMMAND_DURATION_MAX,
+            filter_env_var: FILTER_ENV_VAR.to_string(),
+            fmt_filter: None,
+            ipc_payload_max_bytes: None,
+            ipc_redact_patterns: Vec::new(),
+            ipc_redact_hook: None,
+            env_redact_patterns: ENV_REDACT_PATTERNS.iter().map(ToString::to_string).collect(),
+            capture_panics: true,
+            recording_path: None,
+            recording_max_file_bytes: Recorder::DEFAULT_MAX_FILE_BYTES,
+            recording_max_files: Recorder::DEFAULT_MAX_FILES,
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl Builder {
+    /// Sets the maximum capacity for the channel of events sent from the
+    /// layer to the aggregator.
+    ///
+    /// When this capacity is exhausted, additional events will be dropped.
+    /// Decreasing this value will reduce memory usage, but may result in
+    /// events being dropped more frequently. The capacity is at least 1.
+    ///
+    /// By default, this is 102 400.
+    pub fn event_buffer_capacity(self, event_buffer_capacity: usize) -> Self {
+        Self {
+            event_buffer_capacity: event_buffer_capacity.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the maximum capacity of updates to buffer for each subscribed client.
+    ///
+    /// When this capacity is exhausted, the client is assumed to be inactive,
+    /// and may be disconnected. The capacity is at least 1.
+    ///
+    /// By default, this is 4096.
+    pub fn client_buffer_capacity(self, client_buffer_capacity: usize) -> Self {
+        Self {
+            client_buffer_capacity: client_buffer_capacity.max(1),
+            ..self
+        }
+    }
+
+    /// Sets how frequently updates are published to clients.
+    ///
+    /// A shorter duration will allow clients to update more frequently, but may
+    /// result in the program spending more time preparing updates.
+    ///
+    /// By default, this is 250 milliseconds.
+    pub fn publish_interval(self, publish_interval: Duration) -> Self {
+        Self {
+            publish_interval,
+            ..self
+        }
+    }
+
+    /// Sets how frequently the CPU usage, memory usage, thread count and open file descriptors
+    /// of the process are sampled.
+    ///
+    /// Metrics are read from `/proc`, so they are only sampled on Linux.
+    ///
+    /// By default, this is one second.
+    pub fn metrics_interval(self, metrics_interval: Duration) -> Self {
+        Self {
+            metrics_interval,
+            ..self
+        }
+    }
+
+    /// Sets how long data about completed IPC requests and tasks is kept.
+    ///
+    /// Completed data is sent to clients that connect later, so this controls how much
+    /// history a new client sees. Shorter durations reduce memory usage.
+    ///
+    /// By default, this is one hour.
+    pub fn retention_max_age(self, max_age: Duration) -> Self {
+        Self {
+            retention: Retention {
+                max_age,
+                ..self.retention
+            },
+            ..self
+        }
+    }
+
+    /// Sets how many completed IPC requests and tasks are kept at most.
+    ///
+    /// By default, this is 10 000.
+    pub fn retention_max_count(self, max_count: usize) -> Self {
+        Self {
+            retention: Retention {
+                max_count,
+                ..self.retention
+            },
+            ..self
+        }
+    }
+
+    /// Sets how many bytes completed IPC requests and tasks may take up at most.
+    ///
+    /// Sizes are measured as the encoded size of the data sent to clients, so this is an
+    /// approximation of the actual memory usage. The same limit applies to the log history.
+    ///
+    /// By default, this is 16 MiB.
+    pub fn retention_max_bytes(self, max_bytes: usize) -> Self {
+        Self {
+            retention: Retention {
+                max_bytes,
+                ..self.retention
+            },
+            ..self
+        }
+    }
+
+    /// Sets how many recent log events are kept and replayed to clients when they connect.
+    ///
+    /// This only limits the history, connected clients receive every event regardless. A capacity
+    /// of 0 disables the history.
+    ///
+    /// By default, this is 10 000.
+    pub fn log_history_capacity(self, log_history_capacity: usize) -> Self {
+        Self {
+            log_history_capacity,
+            ..self
+        }
+    }
+
+    /// Sets how long log events are kept and replayed to clients when they connect.
+    ///
+    /// By default, this is ten minutes.
+    pub fn log_history_max_age(self, log_history_max_age: Duration) -> Self {
+        Self {
+            log_history_max_age,
+            ..self
+        }
+    }
+
+    /// Sets the address the gRPC server will listen on.
+    ///
+    /// The port is also advertised through mDNS, so when running several
+    /// instrumented apps on the same machine each needs a distinct port.
+    ///
+    /// By default, this is `0.0.0.0:6669`.
+    pub fn server_addr(self, server_addr: impl Into<SocketAddr>) -> Self {
+        Self {
+            server_addr: server_addr.into(),
+            ..self
+        }
+    }
+
+    /// Sets only the IP the gRPC server will listen on, keeping the configured port.
+    pub fn server_ip(self, ip: impl Into<IpAddr>) -> Self {
+        Self {
+            server_addr: SocketAddr::new(ip.into(), self.server_addr.port()),
+            ..self
+        }
+    }
+
+    /// Sets only the port the gRPC server will listen on, keeping the configured IP.
+    pub fn server_port(self, port: u16) -> Self {
+        Self {
+            server_addr: SocketAddr::new(self.server_addr.ip(), port),
+            ..self
+        }
+    }
+
+    /// Sets the maximum value for the busy time histograms.
+    ///
+    /// Any duration exceeding this will be clamped to this value.
+    ///
+    /// By default, this is one second.
+    pub fn poll_duration_histogram_max(self, max: Duration) -> Self {
+        Self {
+            poll_duration_max: max,
+            ..self
+        }
+    }
+
+    /// Sets the maximum value for the waiting time histograms.
+    ///
+    /// Any duration exceeding this will be clamped to this value.
+    ///
+    /// By default, this is one second.
+    pub fn scheduled_duration_histogram_max(self, max: Duration) -> Self {
+        Self {
+            scheduled_duration_max: max,
+            ..self
+        }
+    }
+
+    /// Sets the maximum value for the duration histograms of the per-command IPC statistics.
+    ///
+    /// Any duration exceeding this will be clamped to this value, percentiles that fall
+    /// among these durations are reported as the highest one.
+    ///
+    /// By default, this is one minute.
+    pub fn command_duration_histogram_max(self, max: Duration) -> Self {
+        Self {
+            command_duration_max: max,
+            ..self
+        }
+    }
+
+    /// Sets the environment variable the filter for the fmt layer is read from.
+    ///
+    /// By default, this is `RUST_LOG`.
+    pub fn filter_env_var(self, filter_env_var: impl Into<String>) -> Self {
+        Self {
+            filter_env_var: filter_env_var.into(),
+            ..self
+        }
+    }
+
+    /// Sets the filter for the fmt layer, ignoring the environment variable.
+    pub fn fmt_filter(self, fmt_filter: filter::Targets) -> Self {
+        Self {
+            fmt_filter: Some(fmt_filter),
+            ..self
+        }
+    }
+
+    /// Captures the arguments and responses of IPC requests, so clients can inspect them.
+    ///
+    /// Values are read from the `value` field of the `ipc.request.deserialize_arg` and
+    /// `ipc.request.serialize_returns` spans, the argument name from the `arg` field.
+    ///
+    /// Each value is truncated to `max_bytes`. Payloads may contain personal or otherwise
+    /// sensitive data, see [`Builder::redact_ipc_args`] to keep specific arguments private.
+    ///
+    /// By default, payloads are not captured.
+    pub fn capture_ipc_payloads(self, max_bytes: usize) -> Self {
+        Self {
+            ipc_payload_max_bytes: Some(max_bytes),
+            ..self
+        }
+    }
+
+    /// Sets patterns for the names of IPC arguments whose values are never captured.
+    ///
+    /// Patterns are matched case-insensitively, `*` matches any sequence of characters
+    /// (e.g. `*password*` or `token`). Redacted arguments are still listed, without their value.
+    pub fn redact_ipc_args<I>(self, patterns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            ipc_redact_patterns: patterns.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets a function deciding whether a captured IPC argument or response is redacted, for
+    /// cases the name patterns of [`Builder::redact_ipc_args`] can't express, e.g. values that
+    /// look like an email address.
+    ///
+    /// The function is called with the argument name, empty for responses, and the full value
+    /// before it is truncated. Values are redacted if either the patterns or the function say so.
+    pub fn redact_ipc_payloads_with<F>(self, redact: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            ipc_redact_hook: Some(RedactHook(Arc::new(redact))),
+            ..self
+        }
+    }
+
+    /// Sets patterns for the names of environment variables, command line flags and configuration
+    /// keys whose values are never sent to clients, replacing the defaults.
+    ///
+    /// Patterns are matched case-insensitively, `*` matches any sequence of characters.
+    ///
+    /// By default, this is `*token*`, `*secret*`, `*password*`, `*passwd*`, `*key*`, `*auth*`
+    /// and `*credential*`.
+    pub fn redact_env<I>(self, patterns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            env_redact_patterns: patterns.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets whether panics are reported to clients, including their message, location, backtrace
+    /// and the spans the panicking thread was in.
+    ///
+    /// This installs a panic hook when the layer is spawned. The previously installed hook is
+    /// still called, so a custom hook should be installed before spawning the layer.
+    ///
+    /// Backtraces are only captured if enabled through `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`,
+    /// like for the default panic hook.
+    ///
+    /// By default, this is `true`.
+    pub fn capture_panics(self, capture_panics: bool) -> Self {
+        Self {
+            capture_panics,
+            ..self
+        }
+    }
+
+    /// Records all data sent to clients to a file, so the session can be analyzed later.
+    ///
+    /// The recording is independent of connected clients and contains everything, including
+    /// the full log. Note that this includes IPC payloads if they are captured.
+    ///
+    /// A recording left at `path` by an earlier session is replaced, including its `.1`, `.2`
+    /// etc. files.
+    ///
+    /// By default, sessions are not recorded.
+    pub fn record_to(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            recording_path: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of a recording file.
+    ///
+    /// Once a file would exceed this size, the recording continues in a new file with
+    /// `.1`, `.2` etc. appended to its name.
+    ///
+    /// By default, this is 64 MiB.
+    pub fn recording_max_file_bytes(self, recording_max_file_bytes: u64) -> Self {
+        Self {
+            recording_max_file_bytes,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of files a recording is split into.
+    ///
+    /// Once the last file is full, the recording stops and a message is printed to stderr. The
+    /// number of files is at least 1.
+    ///
+    /// By default, this is 16, i.e. 1 GiB with the default maximum file size.
+    pub fn recording_max_files(self, recording_max_files: u32) -> Self {
+        Self {
+            recording_max_files: recording_max_files.max(1),
+            ..self
+        }
+    }
+
+    /// Exports spans, IPC requests and log events to an OpenTelemetry collector at `endpoint`
+    /// (e.g. `http://localhost:4317`), using OTLP over gRPC.
+    ///
+    /// The export is independent of connected clients. IPC requests are exported as spans named
+    /// after their command, with child spans for deserializing the arguments, running the handler
+    /// and serializing the response. Batches the collector fails to accept or can't keep up with
+    /// are dropped and reported on stderr.
+    ///
+    /// By default, nothing is exported.
+    pub fn export_otlp(self, endpoint: impl Into<String>) -> Self {
+        Self {
+            otlp_endpoint: Some(endpoint.into()),
+            ..self
+        }
+    }
+
+    /// Spawns the devtools background thread and returns the composed layer.
+    ///
+    /// The returned layer consists of a fmt layer (filtered by the configured fmt filter)
+    /// and the devtools layer, so it can be added to a custom `tracing_subscriber` registry.
+    pub fn spawn<S, A>(self, ctx: &tauri::Context<A>) -> impl tracing_subscriber::Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+        A: tauri::Assets,
+    {
+        let shared = Arc::new(Shared::default());
+
+        let (event_tx, events) = mpsc::channel(self.event_buffer_capacity);
+        let (command_tx, rpcs) = mpsc::channel(256);
+
+        if self.capture_panics {
+            panics::install_hook(shared.clone(), event_tx.clone());
+        }
+
+        let layer = Layer::new(
+            shared.clone(),
+            event_tx,
+            self.event_buffer_capacity,
+            self.poll_duration_max,
+            self.scheduled_duration_max,
+            self.ipc_payload_max_bytes.map(|max_bytes| PayloadCapture {
+                max_bytes,
+                redact: self.ipc_redact_patterns,
+                redact_hook: self.ipc_redact_hook,
+            }),
+        );
+        let aggregator = Aggregator::new(
+            shared.clone(),
+            events,
+            rpcs,
+            self.publish_interval,
+            self.metrics_interval,
+            self.retention,
+            LogHistory::new(
+                self.log_history_capacity,
+                self.log_history_max_age,
+                self.retention.max_bytes,
+            ),
+        )
+        .with_command_duration_max(self.command_duration_max);
+
+        let fmt_filter = self
+            .fmt_filter
+            .unwrap_or_else(|| fmt_filter_from_env(&self.filter_env_var));
+
+        // tokio only emits task spans and waker events at `TRACE`, and only when built with `--cfg tokio_unstable`
+        let layer_filter = filter::Targets::new()
+            .with_default(filter::LevelFilter::DEBUG)
+            .with_target("tokio::task", filter::LevelFilter::TRACE)
+            .with_target("runtime", filter::LevelFilter::TRACE);
+
+        // both filters can be replaced by clients at runtime
+        let (fmt_filter, fmt_filter_handle) = reload::Layer::new(fmt_filter);
+        let (layer_filter, layer_filter_handle) = reload::Layer::new(layer_filter);
+
+        let app_info = AppInfo::new(ctx, self.env_redact_patterns);
+
+        if let Some(path) = self.recording_path {
+            Recorder::new(
+                path,
+                self.recording_max_file_bytes,
+                self.recording_max_files,
+                app_info.package_info(),
+            )
+            .spawn(&command_tx, self.client_buffer_capacity);
+        }
+
+        let exporter = self.otlp_endpoint.and_then(|endpoint| {
+            Exporter::new(endpoint, app_info.package_info())
+                .register(&command_tx, self.client_buffer_capacity)
+        });
+
+        let beacon = Zeroconf::new(self.server_addr.port(), app_info.package_info());
+
+        let server = Server::new(
+            command_tx,
+            shared,
+            Filters::new(layer_filter_handle, fmt_filter_handle),
+            app_info,
+            self.server_addr,
+            self.client_buffer_capacity,
+            self.event_buffer_capacity,
+        );
+
+        thread::Builder::new()
+            .name("console_subscriber".into())
+            .spawn(move || {
+                let _subscriber_guard;
+                // if !self_trace {
+                _subscriber_guard = tracing::subscriber::set_default(
+                    tracing_core::subscriber::NoSubscriber::default(),
+                );
+                // }
+                let runtime = runtime::Builder::new_current_thread()
+                    .enable_io()
+                    .enable_time()
+                    .build()
+                    .expect("console subscriber runtime initialization failed");
+
+                runtime.block_on(async move {
+                    let mdns = spawn_named(beacon.run(), "devtools::mdns");
+
+                    let aggregate = spawn_named(aggregator.run(), "devtools::aggregate");
+
+                    let otlp = exporter.map(|export| spawn_named(export, "devtools::otlp"));
+
+                    spawn_named(server.serve(), "devtools::serve")
+                        .await
+                        .unwrap()
+                        .unwrap();
+
+                    aggregate.abort();
+                    mdns.abort();
+                    if let Some(otlp) = otlp {
+                        otlp.abort();
+                    }
+                });
+            })
+            .expect("console subscriber could not spawn thread");
+
+        tracing_subscriber::fmt::layer()
+            .with_filter(fmt_filter)
+            .and_then(layer.with_filter(layer_filter))
+    }
+
+    /// Spawns the devtools background thread and installs the composed layer as the global default.
+    pub fn try_init<A: tauri::Assets>(
+        self,
+        ctx: &tauri::Context<A>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        tracing_subscriber::registry()
+            .with(self.spawn(ctx))
+            .try_init()?;
+
+        Ok(())
+    }
+
+    /// Like [`Builder::try_init`] but panics if a global default subscriber is already set.
+    pub fn init<A: tauri::Assets>(self, ctx: &tauri::Context<A>) {
+        self.try_init(ctx).unwrap()
+    }
+}
+
+fn fmt_filter_from_env(env_var: &str) -> filter::Targets {
+    std::env::var(env_var)
+        .ok()
+        .and_then(|log_filter| match log_filter.parse::<filter::Targets>() {
+            Ok(targets) => Some(targets),
+            Err(e) => {
+                eprintln!(
+                    "failed to parse filter environment variable `{}={:?}`: {}",
+                    env_var, log_filter, e
+                );
+                None
+            }
+        })
+        .unwrap_or_else(|| {
+            "error"
+                .parse::<filter::Targets>()
+                .expect("`error` filter should always parse successfully")
+        })
+}
@@ -0,0 +1,57 @@
+use tracing_core::Subscriber;
+use tracing_subscriber::{filter::Targets, reload};
+
+/// Handles to the filters of the devtools and fmt layers, used to change them at runtime.
+///
+/// Reloading a filter rebuilds the callsite interest cache, so callsites that were disabled
+/// before are registered with the layers once they become enabled.
+pub(crate) struct Filters {
+    layer: Box<dyn ReloadTargets>,
+    fmt: Box<dyn ReloadTargets>,
+}
+
+/// A `reload::Handle` with the subscriber type erased, so the server doesn't need to know it.
+trait ReloadTargets: Send + Sync {
+    fn reload(&self, targets: Targets) -> Result<(), reload::Error>;
+    fn current(&self) -> Option<Targets>;
+}
+
+impl<S: Subscriber> ReloadTargets for reload::Handle<Targets, S> {
+    fn reload(&self, targets: Targets) -> Result<(), reload::Error> {
+        reload::Handle::reload(self, targets)
+    }
+
+    fn current(&self) -> Option<Targets> {
+        self.clone_current()
+    }
+}
+
+impl Filters {
+    pub(crate) fn new<S: Subscriber>(
+        layer: reload::Handle<Targets, S>,
+        fmt: reload::Handle<Targets, S>,
+    ) -> Self {
+        Self {
+            layer: Box::new(layer),
+            fmt: Box::new(fmt),
+        }
+    }
+
+    pub(crate) fn set_layer_filter(&self, targets: Targets) -> Result<(), reload::Error> {
+        self.layer.reload(targets)
+    }
+
+    pub(crate) fn set_fmt_filter(&self, targets: Targets) -> Result<(), reload::Error> {
+        self.fmt.reload(targets)
+    }
+
+    /// Returns the directives of the devtools layer filter, `None` if the subscriber was dropped.
+    pub(crate) fn layer_filter(&self) -> Option<String> {
+        self.layer.current().map(|targets| targets.to_string())
+    }
+
+    /// Returns the directives of the fmt layer filter, `None` if the subscriber was dropped.
+    pub(crate) fn fmt_filter(&self) -> Option<String> {
+        self.fmt.current().map(|targets| targets.to_string())
+    }
+}
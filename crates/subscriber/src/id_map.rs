@@ -12,9 +12,32 @@ impl<T> IdMap<T> {
         self.0.insert(id, data);
     }
 
+    pub fn get(&self, id: &tracing_core::span::Id) -> Option<&T> {
+        self.0.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &tracing_core::span::Id) -> Option<&mut T> {
+        self.0.get_mut(id)
+    }
+
+    pub fn get_or_default(&mut self, id: tracing_core::span::Id) -> &mut T
+    where
+        T: Default,
+    {
+        self.0.entry(id).or_default()
+    }
+
+    pub fn remove(&mut self, id: &tracing_core::span::Id) -> Option<T> {
+        self.0.remove(id)
+    }
+
     pub(crate) fn all(&self) -> impl Iterator<Item = (&tracing_core::span::Id, &T)> {
         self.0.iter()
     }
+
+    pub(crate) fn all_mut(&mut self) -> impl Iterator<Item = (&tracing_core::span::Id, &mut T)> {
+        self.0.iter_mut()
+    }
 }
 
 impl<T: Unsent> IdMap<T> {
@@ -1,8 +1,5 @@
 use std::{
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
+    sync::Arc,
     time::{Duration, Instant},
 };
 
@@ -12,10 +9,14 @@ use tracing_subscriber::registry::LookupSpan;
 
 use crate::{
     callsites::Callsites,
+    payloads::PayloadCapture,
     stats,
     util::TimeAnchor,
-    visitors::{FieldVisitor, IPCVisitor},
-    Event, Shared,
+    visitors::{
+        AsyncOpVisitor, ErrorVisitor, EventVisitor, FieldVisitor, IPCVisitor, PayloadVisitor,
+        ResourceVisitor, StateUpdateVisitor, TaskVisitor, WakerVisitor, WindowVisitor,
+    },
+    DroppedEvents, Event, Shared,
 };
 
 pub struct Layer {
@@ -38,6 +39,16 @@ pub struct Layer {
     /// TODO make this smaller once all callsites are contained in the tauri crate
     ipc_callsites: Callsites<32>,
 
+    /// Set of callsites for spans representing the emission and delivery of Tauri events.
+    ///
+    /// These are all emitted from within the `tauri` crate, so 8 should be plenty.
+    event_callsites: Callsites<8>,
+
+    /// Set of callsites for events representing window lifecycle changes.
+    ///
+    /// These are all emitted from within the `tauri` crate, so 8 should be plenty.
+    window_callsites: Callsites<8>,
+
     /// Set of callsites for spans representing spawned tasks.
     ///
     /// For task spans, each runtime these will have like, 1-5 callsites in it, max, so
@@ -52,6 +63,17 @@ pub struct Layer {
     /// they might all have their own sets of waker ops.
     waker_callsites: Callsites<16>,
 
+    /// Set of callsites for spans representing resources, e.g. timers, semaphores and channels.
+    ///
+    /// tokio creates resources from about a dozen callsites, 32 leaves room for other runtimes.
+    resource_callsites: Callsites<32>,
+
+    /// Set of callsites for spans representing async operations on resources.
+    async_op_callsites: Callsites<32>,
+
+    /// Set of callsites for events updating the state of a resource.
+    state_update_callsites: Callsites<32>,
+
     /// Maximum value for the poll time histogram.
     ///
     /// By default, this is one second.
@@ -61,11 +83,17 @@ pub struct Layer {
     ///
     /// By default, this is one second.
     max_scheduled_duration_nanos: u64,
+
+    /// How IPC arguments and responses are captured, `None` if they aren't.
+    ipc_payloads: Option<PayloadCapture>,
 }
 
+/// The argument name of an `ipc.request.deserialize_arg` span, in case the value is recorded later.
+struct IPCArgName(String);
+
 impl Layer {
     /// Default maximum capacity for the channel of events sent from a
-    /// [`ConsoleLayer`] to a [`Server`].
+    /// [`Layer`] to the [`Aggregator`](crate::aggregator::Aggregator).
     ///
     /// When this capacity is exhausted, additional events will be dropped.
     /// Decreasing this value will reduce memory usage, but may result in
@@ -87,21 +115,34 @@ impl Layer {
     /// maximum scheduled duration is one second.
     pub const DEFAULT_SCHEDULED_DURATION_MAX: Duration = Duration::from_secs(1);
 
-    pub(crate) fn new(shared: Arc<Shared>, tx: mpsc::Sender<Event>) -> Self {
+    pub(crate) fn new(
+        shared: Arc<Shared>,
+        tx: mpsc::Sender<Event>,
+        event_buffer_capacity: usize,
+        poll_duration_max: Duration,
+        scheduled_duration_max: Duration,
+        ipc_payloads: Option<PayloadCapture>,
+    ) -> Self {
         Self {
             shared,
             tx,
-            flush_threshold: Self::DEFAULT_EVENT_BUFFER_CAPACITY / 2,
+            flush_threshold: event_buffer_capacity / 2,
             base_time: TimeAnchor::new(),
             ipc_callsites: Callsites::default(),
+            event_callsites: Callsites::default(),
+            window_callsites: Callsites::default(),
             spawn_callsites: Callsites::default(),
             waker_callsites: Callsites::default(),
-            max_poll_duration_nanos: Self::DEFAULT_POLL_DURATION_MAX.as_nanos() as u64,
-            max_scheduled_duration_nanos: Self::DEFAULT_SCHEDULED_DURATION_MAX.as_nanos() as u64,
+            resource_callsites: Callsites::default(),
+            async_op_callsites: Callsites::default(),
+            state_update_callsites: Callsites::default(),
+            max_poll_duration_nanos: poll_duration_max.as_nanos() as u64,
+            max_scheduled_duration_nanos: scheduled_duration_max.as_nanos() as u64,
+            ipc_payloads,
         }
     }
 
-    fn send_event(&self, dropped: &AtomicUsize, mk_event: impl FnOnce() -> Event) {
+    fn send_event(&self, dropped: &DroppedEvents, mk_event: impl FnOnce() -> Event) {
         use mpsc::error::TrySendError;
 
         // Return whether or not we actually sent the event.
@@ -120,7 +161,7 @@ impl Layer {
                 // time is very high, maybe the aggregator task hasn't been
                 // polled yet. so... eek?!
 
-                dropped.fetch_add(1, Ordering::Release);
+                dropped.record(1);
             }
         };
 
@@ -134,9 +175,255 @@ impl Layer {
         self.ipc_callsites.contains(meta)
     }
 
+    fn is_tauri_event(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.event_callsites.contains(meta)
+    }
+
+    fn is_window_event(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.window_callsites.contains(meta)
+    }
+
     fn is_spawn(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
         self.spawn_callsites.contains(meta)
     }
+
+    fn is_waker(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.waker_callsites.contains(meta)
+    }
+
+    fn is_resource(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.resource_callsites.contains(meta)
+    }
+
+    fn is_async_op(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.async_op_callsites.contains(meta)
+    }
+
+    fn is_state_update(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        self.state_update_callsites.contains(meta)
+    }
+
+    /// Returns `true` for the spans of the async runtime, which are not streamed as generic spans.
+    ///
+    /// Tasks, resources and async operations are tracked separately, and the runtime's other spans
+    /// (e.g. around every poll) are entered far too often to stream them.
+    fn is_runtime_span(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        let target = meta.target();
+
+        self.is_spawn(meta)
+            || self.is_resource(meta)
+            || self.is_async_op(meta)
+            || target == "tokio"
+            || target.starts_with("tokio::")
+            || target == "runtime"
+            || target.starts_with("runtime::")
+    }
+
+    /// Returns `true` for the spans carrying the arguments and response of IPC requests.
+    fn is_ipc_payload(&self, meta: &'static tracing_core::Metadata<'static>) -> bool {
+        matches!(
+            meta.name(),
+            "ipc.request.deserialize_arg" | "ipc.request.serialize_returns"
+        ) && self.is_ipc_request(meta)
+    }
+
+    /// Records the fields of an IPC payload span, capturing the payload if enabled.
+    ///
+    /// Returns the span's fields without the payload, so it never reaches clients uncaptured.
+    fn record_ipc_payload<S>(
+        &self,
+        id: &tracing_core::span::Id,
+        record: impl FnOnce(&mut PayloadVisitor),
+        ctx: &tracing_subscriber::layer::Context<'_, S>,
+    ) -> Vec<wire::Field>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return Vec::new();
+        };
+
+        let mut payload_visitor = PayloadVisitor::new(span.metadata());
+        record(&mut payload_visitor);
+        let result = payload_visitor.result();
+
+        let (Some(capture), Some(value)) = (&self.ipc_payloads, result.value) else {
+            return result.fields;
+        };
+
+        let is_response = span.name() == "ipc.request.serialize_returns";
+        let name = if is_response {
+            None
+        } else if let Some(name) = result.name {
+            span.extensions_mut().insert(IPCArgName(name.clone()));
+            Some(name)
+        } else {
+            span.extensions()
+                .get::<IPCArgName>()
+                .map(|name| name.0.clone())
+        };
+
+        let request = span
+            .scope()
+            .skip(1)
+            .find(|span| span.name() == "ipc.request" && self.is_ipc_request(span.metadata()));
+
+        if let Some(request) = request {
+            self.send_event(&self.shared.dropped_ipc_events, || Event::IPCPayload {
+                request: request.id(),
+                is_response,
+                payload: capture.capture(name, value),
+            });
+        }
+
+        result.fields
+    }
+
+    /// Sends a resource created by the async runtime, recorded from a new `runtime.resource` span.
+    fn record_resource<S>(
+        &self,
+        id: &tracing_core::span::Id,
+        attrs: &tracing_core::span::Attributes<'_>,
+        ctx: &tracing_subscriber::layer::Context<'_, S>,
+    ) where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let at = Instant::now();
+        let meta = attrs.metadata();
+
+        let mut resource_visitor = ResourceVisitor::new(meta);
+        attrs.record(&mut resource_visitor);
+        let (Some(result), Some(span)) = (resource_visitor.result(), ctx.span(id)) else {
+            return;
+        };
+
+        // resources created by another resource (e.g. the semaphore of a mutex) are created
+        // within its span
+        let parent = span
+            .scope()
+            .skip(1)
+            .find(|span| span.extensions().get::<Arc<stats::ResourceStats>>().is_some())
+            .map(|span| span.id());
+
+        let stats = Arc::new(stats::ResourceStats::new(at));
+
+        self.send_event(&self.shared.dropped_task_events, || Event::Resource {
+            id: id.clone(),
+            metadata: meta,
+            stats: stats.clone(),
+            kind: result.kind,
+            concrete_type: result.concrete_type,
+            fields: result.fields,
+            location: wire::Location {
+                file: result.file,
+                module_path: None,
+                line: result.line,
+                column: result.column,
+            },
+            parent,
+            is_internal: result.is_internal,
+        });
+
+        span.extensions_mut().insert(stats);
+    }
+
+    /// Sends an async operation on a resource, recorded from a new `runtime.resource.async_op` span.
+    fn record_async_op<S>(
+        &self,
+        id: &tracing_core::span::Id,
+        attrs: &tracing_core::span::Attributes<'_>,
+        ctx: &tracing_subscriber::layer::Context<'_, S>,
+    ) where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let at = Instant::now();
+        let meta = attrs.metadata();
+
+        let mut async_op_visitor = AsyncOpVisitor::default();
+        attrs.record(&mut async_op_visitor);
+        let (Some(source), Some(span)) = (async_op_visitor.result(), ctx.span(id)) else {
+            return;
+        };
+
+        // async operations are created within the span of their resource
+        let Some(resource) = span
+            .scope()
+            .skip(1)
+            .find(|span| span.extensions().get::<Arc<stats::ResourceStats>>().is_some())
+        else {
+            return;
+        };
+
+        let stats = Arc::new(stats::AsyncOpStats::new(at));
+
+        self.send_event(&self.shared.dropped_task_events, || Event::AsyncOp {
+            id: id.clone(),
+            metadata: meta,
+            stats: stats.clone(),
+            source,
+            resource: resource.id(),
+        });
+
+        span.extensions_mut().insert(stats);
+    }
+
+    /// Sends the emission or delivery of a Tauri event, recorded from a new `event.emit` or
+    /// `event.deliver` span.
+    fn record_tauri_event<S>(
+        &self,
+        id: &tracing_core::span::Id,
+        attrs: &tracing_core::span::Attributes<'_>,
+        ctx: &tracing_subscriber::layer::Context<'_, S>,
+    ) where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let at = Instant::now();
+        let meta = attrs.metadata();
+
+        let mut event_visitor = EventVisitor::new(meta);
+        attrs.record(&mut event_visitor);
+        let result = event_visitor.result();
+
+        match meta.name() {
+            "event.emit" => {
+                let Some(name) = result.name else {
+                    return;
+                };
+
+                self.send_event(&self.shared.dropped_tauri_events, || {
+                    Event::TauriEventEmitted {
+                        id: id.clone(),
+                        metadata: meta,
+                        fields: result.fields,
+                        name,
+                        source_window: result.source_window,
+                        target_window: result.target_window,
+                        payload_len: result.payload_len,
+                        at,
+                    }
+                });
+            }
+            "event.deliver" => {
+                // listeners are called from within the span of the emitted event
+                let event = ctx.span(id).and_then(|span| {
+                    span.scope().skip(1).find(|span| {
+                        span.name() == "event.emit" && self.is_tauri_event(span.metadata())
+                    })
+                });
+
+                if let Some(event) = event {
+                    self.send_event(&self.shared.dropped_tauri_events, || {
+                        Event::TauriEventDelivered {
+                            event: event.id(),
+                            window: result.window,
+                            at,
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 impl<S> tracing_subscriber::Layer<S> for Layer
@@ -147,18 +434,42 @@ where
         &self,
         meta: &'static tracing_core::Metadata<'static>,
     ) -> tracing_core::Interest {
-        let dropped_counter = match (meta.name(), meta.target()) {
+        match (meta.name(), meta.target()) {
             ("ipc.request", _)
             | ("ipc.request.deserialize_arg", _)
             | ("ipc.request.serialize_returns", _)
             | ("ipc.request.handler", _) => {
                 self.ipc_callsites.insert(meta);
-                &self.shared.dropped_ipc_events
             }
-            _ => &self.shared.dropped_log_events,
-        };
+            ("event.emit", _) | ("event.deliver", _) => {
+                self.event_callsites.insert(meta);
+            }
+            ("window.created", _)
+            | ("window.focused", _)
+            | ("window.resized", _)
+            | ("window.closed", _)
+            | ("webview.navigated", _) => {
+                self.window_callsites.insert(meta);
+            }
+            ("runtime.spawn", _) | (_, "tokio::task") => {
+                self.spawn_callsites.insert(meta);
+            }
+            (_, "tokio::task::waker") | (_, "runtime::waker") => {
+                self.waker_callsites.insert(meta);
+            }
+            ("runtime.resource", _) => {
+                self.resource_callsites.insert(meta);
+            }
+            ("runtime.resource.async_op", _) => {
+                self.async_op_callsites.insert(meta);
+            }
+            (_, "runtime::resource::state_update") => {
+                self.state_update_callsites.insert(meta);
+            }
+            _ => {}
+        }
 
-        self.send_event(dropped_counter, || Event::Metadata(meta));
+        self.send_event(&self.shared.dropped_metadata_events, || Event::Metadata(meta));
 
         tracing_core::Interest::always()
     }
@@ -170,10 +481,66 @@ where
         ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
         let meta = attrs.metadata();
+        if self.is_spawn(meta) {
+            let at = Instant::now();
+
+            let mut task_visitor = TaskVisitor::new(meta);
+            attrs.record(&mut task_visitor);
+            let result = task_visitor.result();
+
+            let stats = Arc::new(stats::TaskStats::new(at));
+
+            // the spans that were active when the task was spawned, innermost first
+            let parents = ctx
+                .lookup_current()
+                .map(|current| current.scope().map(|span| span.id().into()).collect())
+                .unwrap_or_default();
+
+            // async IPC request handlers are spawned as tasks from within the request's spans
+            let ipc_stats = ctx.lookup_current().and_then(|current| {
+                current.scope().find_map(|span| {
+                    span.extensions()
+                        .get::<Arc<stats::IPCRequestStats>>()
+                        .cloned()
+                })
+            });
+            if let Some(ipc_stats) = ipc_stats {
+                ipc_stats.set_task_stats(&stats);
+            }
+
+            self.send_event(&self.shared.dropped_task_events, || Event::Spawn {
+                id: id.clone(),
+                metadata: meta,
+                stats: stats.clone(),
+                kind: result.kind,
+                fields: result.fields,
+                parents,
+                location: wire::Location {
+                    file: result.file.or(meta.file().map(ToString::to_string)),
+                    module_path: meta.module_path().map(ToString::to_string),
+                    line: result.line.or(meta.line()),
+                    column: result.column,
+                },
+            });
+
+            ctx.span(id).unwrap().extensions_mut().insert(stats);
+            return;
+        }
+
+        if self.is_resource(meta) {
+            self.record_resource(id, attrs, &ctx);
+            return;
+        }
+
+        if self.is_async_op(meta) {
+            self.record_async_op(id, attrs, &ctx);
+            return;
+        }
+
         if self.is_ipc_request(meta) {
             let at = Instant::now();
 
-            let mut ipc_visitor = IPCVisitor::new(meta.into());
+            let mut ipc_visitor = IPCVisitor::new(meta);
             attrs.record(&mut ipc_visitor);
 
             if let Some(result) = ipc_visitor.result() {
@@ -181,6 +548,7 @@ where
                     self.max_poll_duration_nanos,
                     self.max_scheduled_duration_nanos,
                     at,
+                    result.kind,
                 ));
 
                 self.send_event(&self.shared.dropped_ipc_events, || {
@@ -197,6 +565,7 @@ where
                         },
                         cmd: result.cmd,
                         kind: result.kind,
+                        window: result.window,
                     }
                 });
 
@@ -214,43 +583,202 @@ where
                 }
             }
         }
+
+        if let Some(span) = ctx.span(id) {
+            if let Some(stats) = span.extensions().get::<Arc<stats::IPCRequestStats>>() {
+                let mut error_visitor = ErrorVisitor::default();
+                attrs.record(&mut error_visitor);
+                if let Some(error) = error_visitor.result() {
+                    stats.fail(error);
+                }
+            }
+        }
+
+        if self.is_tauri_event(meta) {
+            self.record_tauri_event(id, attrs, &ctx);
+        }
+
+        if self.is_runtime_span(meta) {
+            return;
+        }
+
+        let at = Instant::now();
+
+        let fields = if self.is_ipc_payload(meta) {
+            self.record_ipc_payload(id, |visitor| attrs.record(visitor), &ctx)
+        } else {
+            let mut field_visitor = FieldVisitor::new(meta);
+            attrs.record(&mut field_visitor);
+            field_visitor.result()
+        };
+
+        let parent = ctx
+            .span(id)
+            .and_then(|span| span.parent())
+            .map(|parent| parent.id());
+
+        self.send_event(&self.shared.dropped_span_events, || Event::NewSpan {
+            id: id.clone(),
+            parent,
+            metadata: meta,
+            fields,
+            at,
+        });
     }
 
     fn on_record(
         &self,
-        _span: &tracing_core::span::Id,
-        _values: &tracing_core::span::Record<'_>,
-        _ctx: tracing_subscriber::layer::Context<'_, S>,
+        id: &tracing_core::span::Id,
+        values: &tracing_core::span::Record<'_>,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
+        if let Some(span) = ctx.span(id) {
+            if self.is_runtime_span(span.metadata()) {
+                return;
+            }
+
+            let at = Instant::now();
+
+            if let Some(stats) = span.extensions().get::<Arc<stats::IPCRequestStats>>() {
+                let mut error_visitor = ErrorVisitor::default();
+                values.record(&mut error_visitor);
+                if let Some(error) = error_visitor.result() {
+                    stats.fail(error);
+                }
+            }
+
+            let fields = if self.is_ipc_payload(span.metadata()) {
+                self.record_ipc_payload(id, |visitor| values.record(visitor), &ctx)
+            } else {
+                let mut field_visitor = FieldVisitor::new(span.metadata());
+                values.record(&mut field_visitor);
+                field_visitor.result()
+            };
+
+            self.send_event(&self.shared.dropped_span_events, || Event::SpanRecorded {
+                id: id.clone(),
+                fields,
+                at,
+            });
+        }
     }
 
     fn on_follows_from(
         &self,
-        _span: &tracing_core::span::Id,
-        _follows: &tracing_core::span::Id,
-        _ctx: tracing_subscriber::layer::Context<'_, S>,
+        id: &tracing_core::span::Id,
+        follows: &tracing_core::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
+        if let Some(span) = ctx.span(id) {
+            if self.is_runtime_span(span.metadata()) {
+                return;
+            }
+
+            self.send_event(&self.shared.dropped_span_events, || Event::FollowsFrom {
+                id: id.clone(),
+                follows: follows.clone(),
+                at: Instant::now(),
+            });
+        }
     }
 
     fn on_event(
         &self,
         event: &tracing_core::Event<'_>,
-        _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
         let metadata = event.metadata();
 
-        let mut field_visitor = FieldVisitor::new(metadata.into());
+        if self.is_waker(metadata) {
+            let at = Instant::now();
+
+            let mut waker_visitor = WakerVisitor::default();
+            event.record(&mut waker_visitor);
+
+            if let Some((id, mut op)) = waker_visitor.result() {
+                if let Some(span) = ctx.span(&id) {
+                    let exts = span.extensions();
+                    if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+                        if op.is_wake() {
+                            // a task that wakes itself does so while its span is entered
+                            let self_wake = ctx
+                                .current_span()
+                                .id()
+                                .map(|current| current == &id)
+                                .unwrap_or(false);
+                            op = op.self_wake(self_wake);
+                        }
+
+                        stats.record_wake_op(op, at);
+                    }
+                }
+            }
+
+            return;
+        }
+
+        if self.is_state_update(metadata) {
+            let mut state_update_visitor = StateUpdateVisitor::default();
+            event.record(&mut state_update_visitor);
+
+            // state updates are emitted within the span of the resource, or one of its async ops
+            let stats = ctx.event_scope(event).and_then(|mut scope| {
+                scope.find_map(|span| {
+                    span.extensions()
+                        .get::<Arc<stats::ResourceStats>>()
+                        .cloned()
+                })
+            });
+
+            if let Some(stats) = stats {
+                for (name, value, op) in state_update_visitor.result() {
+                    stats.update_attribute(name, value, op);
+                }
+            }
+
+            return;
+        }
+
+        // window events are still sent as log events below
+        if self.is_window_event(metadata) {
+            let mut window_visitor = WindowVisitor::default();
+            event.record(&mut window_visitor);
+
+            if let Some((window, kind)) = window_visitor.result(metadata.name()) {
+                self.send_event(&self.shared.dropped_window_events, || Event::Window {
+                    window,
+                    kind,
+                    at: Instant::now(),
+                });
+            }
+        }
+
+        let mut field_visitor = FieldVisitor::new(metadata);
         event.record(&mut field_visitor);
         let fields = field_visitor.result();
 
-        self.send_event(&self.shared.dropped_log_events, || {
-            Event::Metadata(metadata)
-        });
+        // the spans the event was emitted in, innermost first
+        let mut parents = Vec::new();
+        let mut ipc_request = None;
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                if ipc_request.is_none()
+                    && span.name() == "ipc.request"
+                    && self.is_ipc_request(span.metadata())
+                {
+                    ipc_request = Some(span.id().into());
+                }
+
+                parents.push(span.id().into());
+            }
+        }
 
         self.send_event(&self.shared.dropped_log_events, || Event::LogEvent {
             at: Instant::now(),
             metadata: event.metadata(),
             fields,
+            parents,
+            ipc_request,
         })
     }
 
@@ -262,6 +790,14 @@ where
         if let Some(span) = ctx.span(id) {
             let exts = span.extensions();
 
+            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+                stats.start_poll(Instant::now());
+            }
+
+            if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
+                stats.start_poll(Instant::now());
+            }
+
             if let Some(stats) = exts.get::<Arc<stats::IPCRequestStats>>() {
                 let now = Instant::now();
 
@@ -272,6 +808,13 @@ where
                     _ => {}
                 }
             }
+
+            if !self.is_runtime_span(span.metadata()) {
+                self.send_event(&self.shared.dropped_span_events, || Event::EnterSpan {
+                    id: id.clone(),
+                    at: Instant::now(),
+                });
+            }
         }
     }
 
@@ -279,15 +822,42 @@ where
         if let Some(span) = ctx.span(id) {
             let exts = span.extensions();
 
+            // spans are exited while unwinding, so a panic in a request handler or the task
+            // running it shows up as an exit on a panicking thread
+            let panicking = std::thread::panicking();
+
+            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+                stats.end_poll(Instant::now());
+                if panicking {
+                    stats.panic();
+                }
+            }
+
+            if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
+                stats.end_poll(Instant::now());
+            }
+
             if let Some(stats) = exts.get::<Arc<stats::IPCRequestStats>>() {
                 let now = Instant::now();
                 match span.name() {
                     "ipc.request.deserialize_arg" => stats.end_deserialize(now),
                     "ipc.request.serialize_returns" => stats.end_serialize(now),
-                    "ipc.request.handler" => stats.end_inner(now),
+                    "ipc.request.handler" => {
+                        stats.end_inner(now);
+                        if panicking {
+                            stats.panic();
+                        }
+                    }
                     _ => {}
                 }
             }
+
+            if !self.is_runtime_span(span.metadata()) {
+                self.send_event(&self.shared.dropped_span_events, || Event::ExitSpan {
+                    id: id.clone(),
+                    at: Instant::now(),
+                });
+            }
         }
     }
 
@@ -295,8 +865,27 @@ where
         if let Some(span) = ctx.span(&id) {
             let now = Instant::now();
             let exts = span.extensions();
+            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+                stats.drop_task(now);
+            }
+            if let Some(stats) = exts.get::<Arc<stats::ResourceStats>>() {
+                stats.drop_resource(now);
+            }
+            if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
+                stats.drop_async_op(now);
+            }
+            // the child spans of a request share its stats, but only the request completes it
             if let Some(stats) = exts.get::<Arc<stats::IPCRequestStats>>() {
-                stats.complete(now);
+                if span.name() == "ipc.request" {
+                    stats.complete(now);
+                }
+            }
+
+            if !self.is_runtime_span(span.metadata()) {
+                self.send_event(&self.shared.dropped_span_events, || Event::CloseSpan {
+                    id: id.clone(),
+                    at: now,
+                });
             }
         }
     }
@@ -1,4 +1,5 @@
 mod aggregator;
+mod builder;
 mod zeroconf;
 mod layer;
 mod server;
@@ -7,21 +8,32 @@ mod visitors;
 mod callsites;
 mod stats;
 mod id_map;
+mod log_history;
+mod log_filter;
+mod filters;
+mod payloads;
+mod retention;
+mod app_info;
+mod metrics;
+mod panics;
+mod recording;
+mod otlp;
 
-use aggregator::{Aggregator, Flush};
+use aggregator::Flush;
+use log_filter::LogFilter;
 use wire::instrument::Interests;
-use zeroconf::Zeroconf;
-use layer::Layer;
-use server::Server;
 use std::{
-    sync::{atomic::AtomicUsize, Arc},
-    thread, time::Instant,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
-use tokio::{runtime, sync::mpsc};
-use tracing_subscriber::{filter, prelude::*};
-use util::{spawn_named, TimeAnchor};
+use tokio::sync::{mpsc, oneshot};
+use util::TimeAnchor;
 
-const FILTER_ENV_VAR: &str = "RUST_LOG";
+pub use builder::Builder;
 
 pub fn init<A: tauri::Assets>(ctx: &tauri::Context<A>) {
     try_init(ctx).unwrap()
@@ -30,72 +42,7 @@ pub fn init<A: tauri::Assets>(ctx: &tauri::Context<A>) {
 pub fn try_init<A: tauri::Assets>(
     ctx: &tauri::Context<A>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let shared = Arc::new(Shared::default());
-
-    let (event_tx, events) = mpsc::channel(Layer::DEFAULT_EVENT_BUFFER_CAPACITY);
-    let (command_tx, rpcs) = mpsc::channel(256);
-
-    let layer = Layer::new(shared.clone(), event_tx);
-    let aggregator = Aggregator::new(shared.clone(), events, rpcs);
-    let server = Server::new(command_tx, ctx.package_info().clone());
-    let beacon = Zeroconf::new_from_env(Server::DEFAULT_PORT, ctx.package_info().clone()).unwrap();
-
-    thread::Builder::new()
-        .name("console_subscriber".into())
-        .spawn(move || {
-            let _subscriber_guard;
-            // if !self_trace {
-            _subscriber_guard =
-                tracing::subscriber::set_default(tracing_core::subscriber::NoSubscriber::default());
-            // }
-            let runtime = runtime::Builder::new_current_thread()
-                .enable_io()
-                .enable_time()
-                .build()
-                .expect("console subscriber runtime initialization failed");
-
-            runtime.block_on(async move {
-                let mdns = spawn_named(beacon.run(), "devtools::mdns");
-
-                let aggregate = spawn_named(aggregator.run(), "devtools::aggregate");
-
-                spawn_named(server.serve(), "devtools::serve")
-                    .await
-                    .unwrap()
-                    .unwrap();
-
-                aggregate.abort();
-                mdns.abort();
-            });
-        })
-        .expect("console subscriber could not spawn thread");
-
-    type Filter = filter::Targets;
-
-    let fmt_filter = std::env::var(FILTER_ENV_VAR)
-        .ok()
-        .and_then(|log_filter| match log_filter.parse::<Filter>() {
-            Ok(targets) => Some(targets),
-            Err(e) => {
-                eprintln!(
-                    "failed to parse filter environment variable `{}={:?}`: {}",
-                    FILTER_ENV_VAR, log_filter, e
-                );
-                None
-            }
-        })
-        .unwrap_or_else(|| {
-            "error"
-                .parse::<Filter>()
-                .expect("`error` filter should always parse successfully")
-        });
-
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer().with_filter(fmt_filter.clone()))
-        .with(layer.with_filter(tracing_subscriber::filter::LevelFilter::DEBUG))
-        .try_init()?;
-
-    Ok(())
+    Builder::default().try_init(ctx)
 }
 
 #[derive(Debug, Default)]
@@ -104,11 +51,58 @@ struct Shared {
     /// flushed.
     flush: Flush,
 
-    /// A counter of how many trace events were dropped because the event buffer was at capacity
-    dropped_log_events: AtomicUsize,
+    /// Trace events that were dropped because the event buffer was at capacity
+    dropped_log_events: DroppedEvents,
+
+    /// Metadata that was dropped because the event buffer was at capacity
+    dropped_metadata_events: DroppedEvents,
+
+    /// IPC events that were dropped because the event buffer was at capacity
+    dropped_ipc_events: DroppedEvents,
+
+    /// Task, resource and async operation events that were dropped because the event buffer was at capacity
+    dropped_task_events: DroppedEvents,
+
+    /// Span events that were dropped because the event buffer was at capacity
+    dropped_span_events: DroppedEvents,
+
+    /// Tauri event emissions and deliveries that were dropped because the event buffer was at capacity
+    dropped_tauri_events: DroppedEvents,
+
+    /// Window events that were dropped because the event buffer was at capacity
+    dropped_window_events: DroppedEvents,
+
+    /// Panics that were dropped because the event buffer was at capacity
+    dropped_panic_events: DroppedEvents,
+}
+
+/// Counts the events of one kind that were dropped before reaching clients.
+#[derive(Debug, Default)]
+struct DroppedEvents {
+    /// Events dropped since the last update was published, reset by every published update.
+    pending: AtomicUsize,
+    /// Events dropped since the application was started.
+    total: AtomicUsize,
+}
+
+impl DroppedEvents {
+    fn record(&self, count: usize) {
+        self.pending.fetch_add(count, Ordering::Release);
+        self.total.fetch_add(count, Ordering::Release);
+    }
+
+    fn has_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire) > 0
+    }
+
+    /// Returns the number of events dropped since the last call and resets it.
+    fn take_pending(&self) -> u64 {
+        self.pending.swap(0, Ordering::AcqRel) as u64
+    }
 
-    /// A counter of how many IPC events were dropped because the event buffer was at capacity
-    dropped_ipc_events: AtomicUsize,
+    fn total(&self) -> u64 {
+        self.total.load(Ordering::Acquire) as u64
+    }
 }
 
 enum Event {
@@ -116,7 +110,9 @@ enum Event {
     LogEvent {
         metadata: &'static tracing_core::Metadata<'static>,
         fields: Vec<wire::Field>,
-        at: Instant
+        at: Instant,
+        parents: Vec<wire::SpanId>,
+        ipc_request: Option<wire::Id>,
     },
     IPCRequestInitiated {
         id: tracing_core::span::Id,
@@ -125,12 +121,171 @@ enum Event {
         stats: Arc<stats::IPCRequestStats>,
         metadata: &'static tracing_core::Metadata<'static>,
         fields: Vec<wire::Field>,
-        handler: wire::Location
+        handler: wire::Location,
+        /// The label of the window that issued the request.
+        window: Option<String>,
+    },
+    /// An argument or the response of an IPC request was captured.
+    IPCPayload {
+        /// The ID of the `ipc.request` span.
+        request: tracing_core::span::Id,
+        is_response: bool,
+        payload: wire::ipc::Payload,
+    },
+    Spawn {
+        id: tracing_core::span::Id,
+        metadata: &'static tracing_core::Metadata<'static>,
+        stats: Arc<stats::TaskStats>,
+        kind: wire::tasks::task::Kind,
+        fields: Vec<wire::Field>,
+        parents: Vec<wire::SpanId>,
+        location: wire::Location,
+    },
+    /// The async runtime created a resource, e.g. a timer or a semaphore.
+    Resource {
+        id: tracing_core::span::Id,
+        metadata: &'static tracing_core::Metadata<'static>,
+        stats: Arc<stats::ResourceStats>,
+        kind: String,
+        concrete_type: String,
+        fields: Vec<wire::Field>,
+        location: wire::Location,
+        /// The resource this resource was created in.
+        parent: Option<tracing_core::span::Id>,
+        is_internal: bool,
+    },
+    /// An async operation was started on a resource.
+    AsyncOp {
+        id: tracing_core::span::Id,
+        metadata: &'static tracing_core::Metadata<'static>,
+        stats: Arc<stats::AsyncOpStats>,
+        source: String,
+        resource: tracing_core::span::Id,
+    },
+    NewSpan {
+        id: tracing_core::span::Id,
+        parent: Option<tracing_core::span::Id>,
+        metadata: &'static tracing_core::Metadata<'static>,
+        fields: Vec<wire::Field>,
+        at: Instant,
+    },
+    SpanRecorded {
+        id: tracing_core::span::Id,
+        fields: Vec<wire::Field>,
+        at: Instant,
+    },
+    FollowsFrom {
+        id: tracing_core::span::Id,
+        follows: tracing_core::span::Id,
+        at: Instant,
+    },
+    EnterSpan {
+        id: tracing_core::span::Id,
+        at: Instant,
+    },
+    ExitSpan {
+        id: tracing_core::span::Id,
+        at: Instant,
+    },
+    CloseSpan {
+        id: tracing_core::span::Id,
+        at: Instant,
+    },
+    /// A Tauri event was emitted.
+    TauriEventEmitted {
+        /// The ID of the `event.emit` span.
+        id: tracing_core::span::Id,
+        metadata: &'static tracing_core::Metadata<'static>,
+        fields: Vec<wire::Field>,
+        name: String,
+        source_window: Option<String>,
+        target_window: Option<String>,
+        payload_len: u64,
+        at: Instant,
+    },
+    /// A Tauri event was delivered to a listener.
+    TauriEventDelivered {
+        /// The ID of the `event.emit` span.
+        event: tracing_core::span::Id,
+        window: Option<String>,
+        at: Instant,
+    },
+    /// A window or its webview changed.
+    Window {
+        /// The label of the window.
+        window: String,
+        kind: wire::windows::window_event::Kind,
+        at: Instant,
+    },
+    /// A thread panicked.
+    Panic {
+        message: String,
+        location: Option<wire::Location>,
+        /// The name of the panicking thread.
+        thread: Option<String>,
+        backtrace: String,
+        /// The spans the thread was in, innermost first.
+        spans: Vec<wire::SpanId>,
+        at: Instant,
+    },
+}
+
+impl Event {
+    /// Returns the metadata of the span or event this was recorded from, if it is sent to clients.
+    fn metadata(&self) -> Option<&'static tracing_core::Metadata<'static>> {
+        match self {
+            Event::Metadata(metadata)
+            | Event::LogEvent { metadata, .. }
+            | Event::IPCRequestInitiated { metadata, .. }
+            | Event::Spawn { metadata, .. }
+            | Event::Resource { metadata, .. }
+            | Event::AsyncOp { metadata, .. }
+            | Event::NewSpan { metadata, .. }
+            | Event::TauriEventEmitted { metadata, .. } => Some(metadata),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WakeOp {
+    Wake { self_wake: bool },
+    WakeByRef { self_wake: bool },
+    Clone,
+    Drop,
+}
+
+impl WakeOp {
+    /// Returns `true` if `self` is a `Wake` or `WakeByRef` event.
+    fn is_wake(self) -> bool {
+        matches!(self, Self::Wake { .. } | Self::WakeByRef { .. })
+    }
+
+    fn self_wake(self, self_wake: bool) -> Self {
+        match self {
+            Self::Wake { .. } => Self::Wake { self_wake },
+            Self::WakeByRef { .. } => Self::WakeByRef { self_wake },
+            x => x,
+        }
     }
 }
 
 enum Command {
     Instrument(Watch<wire::instrument::Update>),
+    UpdateInterests {
+        peer: SocketAddr,
+        interests: Interests,
+        /// Receives the number of watches that were updated.
+        updated: oneshot::Sender<usize>,
+    },
+    ListWindows {
+        /// Receives the open windows.
+        windows: oneshot::Sender<Vec<wire::windows::Window>>,
+    },
+    ListCommands {
+        /// Receives the names of all commands that were invoked so far.
+        commands: oneshot::Sender<Vec<String>>,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -143,13 +298,24 @@ enum Include {
 struct Watch<T> {
     tx: mpsc::Sender<Result<T, tonic::Status>>,
     interests: Interests,
+    /// Which log events the client wants to receive.
+    log_filter: LogFilter,
+    /// The address of the client that opened this watch, used to route `UpdateInterests` requests.
+    peer: Option<SocketAddr>,
+    /// Whether the watch is kept when its buffer is full, instead of being dropped.
+    ///
+    /// Updates are then skipped until the buffer has room again, at which point the watch
+    /// catches up with an update containing the full state.
+    lossless: bool,
+    /// The number of updates skipped since the buffer of a `lossless` watch was full.
+    skipped: u64,
 }
 
-impl<T: Clone> Watch<T> {
+impl<T> Watch<T> {
     // TODO make return type more meaningful
-    fn update(&self, update: &T) -> bool {
+    fn update(&self, update: T) -> bool {
         if let Ok(reserve) = self.tx.try_reserve() {
-            reserve.send(Ok(update.clone()));
+            reserve.send(Ok(update));
             true
         } else {
             false
@@ -0,0 +1,480 @@
+use std::{cmp::Reverse, fmt, str::FromStr, sync::Arc};
+use tracing_core::Metadata;
+use tracing_subscriber::filter::LevelFilter;
+
+/// Filter deciding which log events are sent to a client.
+///
+/// Directives use the same syntax as `tracing_subscriber::EnvFilter`, a comma separated list of
+/// `target[span{field=value}]=level`, where every part is optional:
+///
+/// - `my_app=trace` enables all events with a target starting with `my_app` up to `TRACE`.
+/// - `warn` enables events of all targets up to `WARN`.
+/// - `tauri[ipc.request]=debug` enables events emitted inside `ipc.request` spans.
+/// - `[ipc.request{cmd="greet"}]=trace` additionally requires the span to have a matching field.
+/// - `[{user_id=42}]=trace` matches events that have the field themselves, or are emitted inside a span that does.
+///
+/// When several directives match an event, the most specific one decides. Events matching no
+/// directive are not sent. An empty filter sends all events.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LogFilter {
+    /// Directives ordered from most to least specific.
+    directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone)]
+struct Directive {
+    target: Option<String>,
+    span: Option<String>,
+    fields: Vec<FieldMatch>,
+    level: LevelFilter,
+}
+
+#[derive(Debug, Clone)]
+struct FieldMatch {
+    name: String,
+    /// The expected value, `None` if the field only has to be present.
+    value: Option<String>,
+}
+
+/// A span a log event was emitted in, used to evaluate span directives.
+#[derive(Debug, Clone)]
+pub(crate) struct ScopeSpan {
+    pub(crate) metadata: &'static Metadata<'static>,
+    /// The values recorded for the span when the event was emitted.
+    pub(crate) fields: Arc<Vec<wire::Field>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseError {
+    directive: String,
+    reason: &'static str,
+}
+
+impl LogFilter {
+    /// Returns `true` if an event with the given fields, emitted in `scope` (innermost span first), should be sent.
+    pub(crate) fn enabled(
+        &self,
+        metadata: &'static Metadata<'static>,
+        fields: &[wire::Field],
+        scope: &[ScopeSpan],
+    ) -> bool {
+        if self.directives.is_empty() {
+            return true;
+        }
+
+        self.directives
+            .iter()
+            .find(|directive| directive.matches(metadata, fields, scope))
+            .is_some_and(|directive| directive.level >= *metadata.level())
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut directives = split_directives(s)
+            .map(str::parse)
+            .collect::<Result<Vec<Directive>, _>>()?;
+
+        // directives with span or field matches are more specific than any plain target, after
+        // that longer targets are more specific
+        directives.sort_by_key(|directive| {
+            Reverse((
+                directive.span.is_some() || !directive.fields.is_empty(),
+                directive.target.as_ref().map_or(0, String::len),
+            ))
+        });
+
+        Ok(Self { directives })
+    }
+}
+
+impl Directive {
+    fn matches(
+        &self,
+        metadata: &'static Metadata<'static>,
+        fields: &[wire::Field],
+        scope: &[ScopeSpan],
+    ) -> bool {
+        if let Some(target) = &self.target {
+            if !metadata.target().starts_with(target.as_str()) {
+                return false;
+            }
+        }
+
+        match &self.span {
+            Some(name) => scope.iter().any(|span| {
+                span.metadata.name() == name && self.fields_match(span.metadata, &span.fields)
+            }),
+            None if self.fields.is_empty() => true,
+            None => {
+                self.fields_match(metadata, fields)
+                    || scope
+                        .iter()
+                        .any(|span| self.fields_match(span.metadata, &span.fields))
+            }
+        }
+    }
+
+    fn fields_match(&self, metadata: &'static Metadata<'static>, fields: &[wire::Field]) -> bool {
+        self.fields.iter().all(|expected| {
+            fields.iter().any(|field| {
+                field_name(metadata, field) == Some(expected.name.as_str())
+                    && match (&expected.value, &field.value) {
+                        (None, _) => true,
+                        (Some(expected), Some(value)) => value_matches(value, expected),
+                        (Some(_), None) => false,
+                    }
+            })
+        })
+    }
+}
+
+impl FromStr for Directive {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |reason| ParseError {
+            directive: s.to_string(),
+            reason,
+        };
+
+        // field values may contain `=`, so the level is whatever follows the span selector
+        let (selector, level) = match s.rfind(']') {
+            Some(end) => match &s[end + 1..] {
+                "" => (&s[..=end], None),
+                rest => (
+                    &s[..=end],
+                    Some(
+                        rest.strip_prefix('=')
+                            .ok_or_else(|| err("expected `=` after span selector"))?,
+                    ),
+                ),
+            },
+            None => match s.split_once('=') {
+                Some((target, level)) => (target, Some(level)),
+                None => (s, None),
+            },
+        };
+
+        let (target, span_selector) = match selector.split_once('[') {
+            Some((target, rest)) => (
+                target,
+                Some(
+                    rest.strip_suffix(']')
+                        .ok_or_else(|| err("unclosed `[`"))?,
+                ),
+            ),
+            None => (selector, None),
+        };
+
+        let (span, fields) = match span_selector {
+            Some(span_selector) => match span_selector.split_once('{') {
+                Some((span, fields)) => (
+                    span,
+                    fields
+                        .strip_suffix('}')
+                        .ok_or_else(|| err("unclosed `{`"))?,
+                ),
+                None => (span_selector, ""),
+            },
+            None => ("", ""),
+        };
+
+        let fields = split_fields(fields)
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| match field.split_once('=') {
+                Some((name, value)) => FieldMatch {
+                    name: name.trim().to_string(),
+                    value: Some(value.trim().trim_matches('"').to_string()),
+                },
+                None => FieldMatch {
+                    name: field.to_string(),
+                    value: None,
+                },
+            })
+            .collect();
+
+        let target = target.trim();
+        let span = span.trim();
+
+        let level = match level {
+            Some(level) => level
+                .trim()
+                .parse()
+                .map_err(|_| err("invalid level"))?,
+            // a bare level like `debug` applies to all targets
+            None if span_selector.is_none() => {
+                if let Ok(level) = target.parse() {
+                    return Ok(Self {
+                        target: None,
+                        span: None,
+                        fields: Vec::new(),
+                        level,
+                    });
+                }
+
+                LevelFilter::TRACE
+            }
+            None => LevelFilter::TRACE,
+        };
+
+        Ok(Self {
+            target: (!target.is_empty()).then(|| target.to_string()),
+            span: (!span.is_empty()).then(|| span.to_string()),
+            fields,
+            level,
+        })
+    }
+}
+
+/// Splits a filter string at the commas that are not part of a span selector.
+fn split_directives(s: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut directives = Vec::new();
+
+    for (idx, c) in s.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                directives.push(&s[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    directives.push(&s[start..]);
+
+    directives
+        .into_iter()
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+}
+
+/// Splits the fields of a span selector at the commas that are not part of a quoted value.
+fn split_fields(s: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    let mut start = 0;
+    let mut fields = Vec::new();
+
+    for (idx, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(&s[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&s[start..]);
+
+    fields.into_iter()
+}
+
+fn field_name<'a>(
+    metadata: &'static Metadata<'static>,
+    field: &'a wire::Field,
+) -> Option<&'a str> {
+    match field.name.as_ref()? {
+        wire::field::Name::StrName(name) => Some(name),
+        wire::field::Name::NameIdx(idx) => metadata
+            .fields()
+            .iter()
+            .nth(*idx as usize)
+            .map(|field| field.name()),
+    }
+}
+
+fn value_matches(value: &wire::field::Value, expected: &str) -> bool {
+    match value {
+        // strings recorded through `Debug` are quoted
+        wire::field::Value::DebugVal(value) | wire::field::Value::StrVal(value) => {
+            value == expected || value.trim_matches('"') == expected
+        }
+        wire::field::Value::U64Val(value) => value.to_string() == expected,
+        wire::field::Value::I64Val(value) => value.to_string() == expected,
+        wire::field::Value::BoolVal(value) => value.to_string() == expected,
+        wire::field::Value::F64Val(value) => expected.parse() == Ok(*value),
+        wire::field::Value::U128Val(value) => u128::from(value.clone()).to_string() == expected,
+        wire::field::Value::I128Val(value) => i128::from(value.clone()).to_string() == expected,
+        // nested values can't be matched by directives
+        wire::field::Value::StructuredVal(_) => false,
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid directive `{}`: {}", self.directive, self.reason)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_core::{callsite::Callsite, metadata::Kind, Interest, Level};
+
+    macro_rules! callsite {
+        ($callsite:ident, $metadata:ident, $name:expr, $target:expr, $level:expr, $fields:expr, $kind:expr) => {
+            struct $callsite;
+
+            static $metadata: Metadata<'static> = tracing_core::metadata! {
+                name: $name,
+                target: $target,
+                level: $level,
+                fields: $fields,
+                callsite: &$callsite,
+                kind: $kind,
+            };
+
+            impl Callsite for $callsite {
+                fn set_interest(&self, _: Interest) {}
+
+                fn metadata(&self) -> &Metadata<'_> {
+                    &$metadata
+                }
+            }
+        };
+    }
+
+    callsite!(
+        EventCallsite,
+        EVENT,
+        "event",
+        "my_app::db",
+        Level::DEBUG,
+        &["message", "user_id"],
+        Kind::EVENT
+    );
+    callsite!(
+        SpanCallsite,
+        SPAN,
+        "ipc.request",
+        "tauri::ipc",
+        Level::INFO,
+        &["cmd"],
+        Kind::SPAN
+    );
+
+    fn field(idx: u64, value: wire::field::Value) -> wire::Field {
+        wire::Field {
+            name: Some(wire::field::Name::NameIdx(idx)),
+            value: Some(value),
+            metadata_id: None,
+        }
+    }
+
+    fn request(cmd: &str) -> ScopeSpan {
+        ScopeSpan {
+            metadata: &SPAN,
+            fields: Arc::new(vec![field(0, wire::field::Value::DebugVal(format!("{cmd:?}")))]),
+        }
+    }
+
+    fn enabled(filter: &str, fields: &[wire::Field], scope: &[ScopeSpan]) -> bool {
+        filter
+            .parse::<LogFilter>()
+            .unwrap()
+            .enabled(&EVENT, fields, scope)
+    }
+
+    #[test]
+    fn parses_targets_and_levels() {
+        let filter: LogFilter = "warn, my_app=trace ,my_app::db".parse().unwrap();
+
+        let directives: Vec<_> = filter
+            .directives
+            .iter()
+            .map(|directive| (directive.target.as_deref(), directive.level))
+            .collect();
+
+        // ordered from most to least specific
+        assert_eq!(
+            directives,
+            [
+                (Some("my_app::db"), LevelFilter::TRACE),
+                (Some("my_app"), LevelFilter::TRACE),
+                (None, LevelFilter::WARN),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_span_selectors() {
+        let filter: LogFilter = r#"tauri[ipc.request{cmd="greet", id}]=debug,[{query="a=b,c"}]"#
+            .parse()
+            .unwrap();
+
+        let [span, fields] = &filter.directives[..] else {
+            panic!("expected two directives, got {:?}", filter.directives);
+        };
+
+        assert_eq!(span.target.as_deref(), Some("tauri"));
+        assert_eq!(span.span.as_deref(), Some("ipc.request"));
+        assert_eq!(span.level, LevelFilter::DEBUG);
+        let span_fields: Vec<_> = span
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_deref()))
+            .collect();
+        assert_eq!(span_fields, [("cmd", Some("greet")), ("id", None)]);
+
+        assert_eq!(fields.target, None);
+        assert_eq!(fields.span, None);
+        assert_eq!(fields.level, LevelFilter::TRACE);
+        assert_eq!(fields.fields[0].name, "query");
+        assert_eq!(fields.fields[0].value.as_deref(), Some("a=b,c"));
+    }
+
+    #[test]
+    fn rejects_invalid_directives() {
+        for (filter, reason) in [
+            ("my_app=loud", "invalid level"),
+            ("[ipc.request", "unclosed `[`"),
+            ("[ipc.request{cmd=1]=info", "unclosed `{`"),
+            ("[ipc.request]info", "expected `=` after span selector"),
+        ] {
+            let err = filter.parse::<LogFilter>().unwrap_err();
+            assert_eq!(err.reason, reason, "{filter}");
+        }
+    }
+
+    #[test]
+    fn matches_targets_and_levels() {
+        assert!(enabled("", &[], &[]));
+        assert!(enabled("debug", &[], &[]));
+        assert!(enabled("my_app=debug", &[], &[]));
+        assert!(!enabled("info", &[], &[]));
+        assert!(!enabled("my_app=info", &[], &[]));
+        assert!(!enabled("other=trace", &[], &[]));
+        // the most specific directive decides
+        assert!(enabled("my_app=error,my_app::db=debug", &[], &[]));
+        assert!(!enabled("my_app::db=error,debug", &[], &[]));
+    }
+
+    #[test]
+    fn matches_spans_and_fields() {
+        let greet = [request("greet")];
+        let other = [request("other")];
+
+        assert!(enabled("[ipc.request]", &[], &greet));
+        assert!(enabled(r#"[ipc.request{cmd="greet"}]"#, &[], &greet));
+        assert!(enabled("[ipc.request{cmd}]=debug", &[], &other));
+        assert!(!enabled(r#"[ipc.request{cmd="greet"}]"#, &[], &other));
+        assert!(!enabled("[ipc.request]", &[], &[]));
+        assert!(!enabled("[ipc.request]=info", &[], &greet));
+
+        let user = [field(1, wire::field::Value::U64Val(42))];
+        assert!(enabled("[{user_id=42}]", &user, &[]));
+        assert!(!enabled("[{user_id=43}]", &user, &[]));
+        assert!(!enabled("[{user_id}]", &[], &[]));
+        // fields of the spans the event was emitted in match too
+        assert!(enabled(r#"[{cmd="greet"}]"#, &[], &greet));
+    }
+}
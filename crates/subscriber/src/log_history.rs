@@ -0,0 +1,121 @@
+use crate::log_filter::ScopeSpan;
+use prost::Message;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Ring buffer of recent log events.
+///
+/// Events are kept after they have been published so that clients connecting later can be sent
+/// what led up to the current state. The newest `unsent` events have not been published yet.
+///
+/// The limits only apply to published events while clients are connected, unpublished events are
+/// kept until the next publish so connected clients receive every event.
+pub(crate) struct LogHistory {
+    events: VecDeque<Entry>,
+    /// Encoded size of all events in the buffer.
+    bytes: usize,
+    /// Number of events at the back of the buffer that have not been published yet.
+    unsent: usize,
+
+    max_count: usize,
+    max_age: Duration,
+    max_bytes: usize,
+}
+
+struct Entry {
+    at: Instant,
+    size: usize,
+    entry: LogEntry,
+}
+
+/// A log event together with what is needed to evaluate client filters for it.
+#[derive(Clone)]
+pub(crate) struct LogEntry {
+    pub(crate) event: wire::log::Event,
+    pub(crate) metadata: &'static tracing_core::Metadata<'static>,
+    /// The spans the event was emitted in, innermost first.
+    pub(crate) scope: Vec<ScopeSpan>,
+}
+
+impl LogHistory {
+    /// Default number of log events kept for late-connecting clients.
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Default duration log events are kept for late-connecting clients.
+    pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+    pub(crate) fn new(max_count: usize, max_age: Duration, max_bytes: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            bytes: 0,
+            unsent: 0,
+            max_count,
+            max_age,
+            max_bytes,
+        }
+    }
+
+    /// Appends a new event and evicts events exceeding the configured limits.
+    ///
+    /// `keep_unsent` should be `true` while clients are connected, the event is kept until it was
+    /// published then.
+    pub(crate) fn push(&mut self, at: Instant, entry: LogEntry, keep_unsent: bool) {
+        let size = entry.event.encoded_len();
+
+        self.bytes += size;
+        self.unsent += 1;
+        self.events.push_back(Entry { at, size, entry });
+
+        self.evict(at, keep_unsent)
+    }
+
+    /// Evicts events exceeding the configured limits, except for unsent events if `keep_unsent` is
+    /// `true`.
+    pub(crate) fn evict(&mut self, now: Instant, keep_unsent: bool) {
+        while let Some(oldest) = self.events.front() {
+            let expired = now.saturating_duration_since(oldest.at) > self.max_age;
+            if !expired && self.events.len() <= self.max_count && self.bytes <= self.max_bytes {
+                break;
+            }
+
+            if self.unsent == self.events.len() {
+                if keep_unsent {
+                    break;
+                }
+                self.unsent -= 1;
+            }
+
+            self.bytes -= oldest.size;
+            self.events.pop_front();
+        }
+    }
+
+    pub(crate) fn has_unsent(&self) -> bool {
+        self.unsent > 0
+    }
+
+    /// Returns all events that were already published.
+    pub(crate) fn sent(&self) -> Vec<LogEntry> {
+        let sent = self.events.len() - self.unsent;
+
+        self.events
+            .iter()
+            .take(sent)
+            .map(|entry| entry.entry.clone())
+            .collect()
+    }
+
+    /// Returns all events that were not published yet and marks them as published.
+    pub(crate) fn take_unsent(&mut self) -> Vec<LogEntry> {
+        let sent = self.events.len() - self.unsent;
+        self.unsent = 0;
+
+        self.events
+            .iter()
+            .skip(sent)
+            .map(|entry| entry.entry.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,212 @@
+use crate::util::TimeAnchor;
+use std::{collections::HashMap, time::Instant};
+
+/// Samples the resource usage of the process.
+///
+/// Metrics are read from `/proc/self`, so they are only available on Linux. No samples are taken
+/// on Windows and macOS.
+#[derive(Default)]
+pub(crate) struct Sampler {
+    /// The previous sample, used to compute the CPU usage in between.
+    previous: Option<CpuTimes>,
+}
+
+/// The CPU time used so far, in clock ticks.
+struct CpuTimes {
+    at: Instant,
+    process: u64,
+    threads: HashMap<u64, u64>,
+}
+
+impl Sampler {
+    /// Takes a sample, `None` if metrics aren't available on this platform.
+    pub(crate) fn sample(&mut self, base_time: &TimeAnchor) -> Option<wire::metrics::Sample> {
+        #[cfg(target_os = "linux")]
+        return self.sample_proc(base_time);
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = base_time;
+            None
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sample_proc(&mut self, base_time: &TimeAnchor) -> Option<wire::metrics::Sample> {
+        use std::fs;
+
+        let at = Instant::now();
+
+        let stat = proc::Stat::read("/proc/self/stat")?;
+        let status = fs::read_to_string("/proc/self/status").ok()?;
+        let open_fds = fs::read_dir("/proc/self/fd").map_or(0, |fds| fds.count());
+
+        let mut threads = Vec::new();
+        if let Ok(tasks) = fs::read_dir("/proc/self/task") {
+            for task in tasks.flatten() {
+                let Some(tid) = task.file_name().to_str().and_then(|tid| tid.parse().ok()) else {
+                    continue;
+                };
+
+                // the thread may have exited in the meantime
+                if let Some(stat) = proc::Stat::read(task.path().join("stat")) {
+                    threads.push((tid, stat));
+                }
+            }
+        }
+
+        let previous = self.previous.replace(CpuTimes {
+            at,
+            process: stat.cpu_ticks,
+            threads: threads
+                .iter()
+                .map(|(tid, stat)| (*tid, stat.cpu_ticks))
+                .collect(),
+        });
+
+        let clock_ticks = proc::clock_ticks();
+        let cpu_usage = |ticks: u64, previous_ticks: Option<u64>| match (&previous, previous_ticks)
+        {
+            (Some(previous), Some(previous_ticks)) => {
+                let elapsed = at.saturating_duration_since(previous.at).as_secs_f64();
+                let busy = ticks.saturating_sub(previous_ticks) as f64 / clock_ticks;
+
+                if elapsed > 0.0 {
+                    busy / elapsed
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+
+        let thread_samples = threads
+            .into_iter()
+            .map(|(tid, stat)| wire::metrics::ThreadSample {
+                tid,
+                cpu_usage: cpu_usage(
+                    stat.cpu_ticks,
+                    previous
+                        .as_ref()
+                        .and_then(|previous| previous.threads.get(&tid).copied()),
+                ),
+                name: stat.name,
+            })
+            .collect();
+
+        Some(wire::metrics::Sample {
+            at: Some(base_time.to_timestamp(at)),
+            cpu_usage: cpu_usage(
+                stat.cpu_ticks,
+                previous.as_ref().map(|previous| previous.process),
+            ),
+            rss_bytes: proc::status_kb(&status, "VmRSS").unwrap_or_default() * 1024,
+            virtual_bytes: stat.virtual_bytes,
+            threads: stat.threads,
+            open_fds: open_fds as u32,
+            thread_samples,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod proc {
+    use std::{fs, path::Path, sync::OnceLock};
+
+    /// Returns the number of clock ticks per second (`USER_HZ`) the kernel reports CPU times in.
+    pub(super) fn clock_ticks() -> f64 {
+        static CLOCK_TICKS: OnceLock<f64> = OnceLock::new();
+
+        *CLOCK_TICKS.get_or_init(|| {
+            // SAFETY: `sysconf` has no preconditions
+            match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+                ticks if ticks > 0 => ticks as f64,
+                // 100 is the value on all common architectures
+                _ => 100.0,
+            }
+        })
+    }
+
+    /// The fields of a `/proc/<pid>/stat` or `/proc/<pid>/task/<tid>/stat` file we care about.
+    pub(super) struct Stat {
+        pub(super) name: String,
+        /// User and system CPU time.
+        pub(super) cpu_ticks: u64,
+        pub(super) threads: u32,
+        pub(super) virtual_bytes: u64,
+    }
+
+    impl Stat {
+        pub(super) fn read(path: impl AsRef<Path>) -> Option<Self> {
+            Self::parse(&fs::read_to_string(path).ok()?)
+        }
+
+        pub(super) fn parse(stat: &str) -> Option<Self> {
+            // the name is wrapped in parentheses and may contain spaces or parentheses itself
+            let name_start = stat.find('(')?;
+            let name_end = stat.rfind(')')?;
+            let name = stat.get(name_start + 1..name_end)?.to_string();
+
+            // the remaining fields start with the 3rd field (`state`), see `proc(5)`
+            let fields: Vec<_> = stat.get(name_end + 1..)?.split_whitespace().collect();
+            let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+
+            Some(Self {
+                name,
+                cpu_ticks: field(14)? + field(15)?,
+                threads: field(20)? as u32,
+                virtual_bytes: field(23)?,
+            })
+        }
+    }
+
+    /// Reads a value in kB from the contents of a `/proc/<pid>/status` file.
+    pub(super) fn status_kb(status: &str, key: &str) -> Option<u64> {
+        status.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix(':')?;
+            value.trim().strip_suffix("kB")?.trim().parse().ok()
+        })
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::proc::{status_kb, Stat};
+
+    #[test]
+    fn parses_stat() {
+        let stat = Stat::parse(
+            "1234 (tokio-runtime) w) S 1 1234 1234 0 -1 4194560 2170 0 0 0 \
+             25 7 0 0 20 0 12 0 3805 2405146624 5316 18446744073709551615",
+        )
+        .unwrap();
+
+        assert_eq!(stat.name, "tokio-runtime) w");
+        assert_eq!(stat.cpu_ticks, 32);
+        assert_eq!(stat.threads, 12);
+        assert_eq!(stat.virtual_bytes, 2405146624);
+    }
+
+    #[test]
+    fn rejects_truncated_stat() {
+        assert!(Stat::parse("1234 (app) S 1 1234").is_none());
+        assert!(Stat::parse("1234 app S").is_none());
+    }
+
+    #[test]
+    fn reads_own_stat() {
+        let stat = Stat::read("/proc/self/stat").unwrap();
+        assert!(stat.threads >= 1);
+        assert!(stat.virtual_bytes > 0);
+    }
+
+    #[test]
+    fn parses_status_kb() {
+        let status = "Name:\tapp\nVmPeak:\t  20000 kB\nVmRSS:\t   5316 kB\nThreads:\t12\n";
+
+        assert_eq!(status_kb(status, "VmRSS"), Some(5316));
+        assert_eq!(status_kb(status, "VmPeak"), Some(20000));
+        assert_eq!(status_kb(status, "Threads"), None);
+        assert_eq!(status_kb(status, "VmSwap"), None);
+    }
+}
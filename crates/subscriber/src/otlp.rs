@@ -0,0 +1,1269 @@
+use crate::{log_filter::LogFilter, Command, Watch};
+use std::{
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tonic::transport::{Channel, Endpoint};
+use wire::{
+    instrument::Interests,
+    otlp::{
+        collector::{
+            logs::v1::{logs_service_client::LogsServiceClient, ExportLogsServiceRequest},
+            trace::v1::{trace_service_client::TraceServiceClient, ExportTraceServiceRequest},
+        },
+        common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue},
+        logs::v1::{LogRecord, ResourceLogs, ScopeLogs, SeverityNumber},
+        resource::v1::Resource,
+        trace::v1::{span, status, ResourceSpans, ScopeSpans, Span, Status},
+    },
+    spans::span_event,
+};
+
+/// Exports spans, IPC requests and log events to an OpenTelemetry collector using OTLP over gRPC.
+///
+/// Like the recorder, the exporter is registered with the aggregator as a client and converts the
+/// published updates. Spans are exported once they are closed, IPC requests become `SERVER` spans
+/// named after their command, with a child span for every phase of the request.
+///
+/// The exporter is a lossless client, so the aggregator never drops it. Instead, converted batches
+/// are queued for the collector and dropped (and reported) when the collector can't keep up.
+pub(crate) struct Exporter {
+    endpoint: String,
+    resource: Resource,
+    /// The metadata of all spans and events, by metadata ID.
+    metadata: HashMap<u64, wire::Metadata>,
+    /// Spans that have not been closed yet, by span ID.
+    open_spans: HashMap<u64, OpenSpan>,
+    /// IPC requests whose span has not been closed yet, by request ID (which is the ID of their span).
+    requests: HashMap<u64, Request>,
+    /// The time of the latest exported log event, so the log history isn't exported twice
+    /// when the exporter catches up after falling behind.
+    last_log_at: u64,
+}
+
+/// The spans and log records converted from one update.
+#[derive(Default)]
+struct Batch {
+    spans: Vec<Span>,
+    log_records: Vec<LogRecord>,
+}
+
+/// The connection to the collector.
+struct Collector {
+    endpoint: String,
+    resource: Resource,
+    traces: TraceServiceClient<Channel>,
+    logs: LogsServiceClient<Channel>,
+    /// Whether the last export failed, so a collector that is down is only reported once.
+    failing: bool,
+}
+
+/// Identifies a span in the exported traces.
+#[derive(Clone, Copy)]
+struct SpanContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+enum OpenSpan {
+    Span(PendingSpan),
+    /// An `ipc.request.*` span, these are exported as the phase spans of their request instead.
+    ///
+    /// Spans and events inside of it are attached to the phase span.
+    Phase {
+        context: SpanContext,
+        start_time_unix_nano: u64,
+    },
+}
+
+struct PendingSpan {
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    metadata_id: Option<u64>,
+    /// All values recorded so far.
+    fields: Vec<wire::Field>,
+    start_time_unix_nano: u64,
+    /// The spans this span follows from.
+    links: Vec<SpanContext>,
+    /// The span IDs of the phase spans, if this is an IPC request. Assigned when first referenced.
+    phase_ids: [Option<[u8; 8]>; 3],
+}
+
+struct Request {
+    request: wire::ipc::Request,
+    stats: Option<wire::ipc::Stats>,
+}
+
+/// The phases of an IPC request, see `wire::ipc::Stats`.
+#[derive(Clone, Copy)]
+enum Phase {
+    DeserializeRequest,
+    Handler,
+    SerializeResponse,
+}
+
+impl Exporter {
+    /// How long to wait for the collector to accept a batch before it is dropped.
+    const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// The maximum number of batches waiting for the collector, newer batches are dropped
+    /// while the queue is full.
+    const MAX_QUEUED_BATCHES: usize = 64;
+
+    /// The maximum number of spans tracked until they are closed.
+    ///
+    /// Spans whose close event was dropped would be tracked forever otherwise, so the oldest
+    /// quarter of them is evicted when this is exceeded.
+    const MAX_OPEN_SPANS: usize = 10_000;
+
+    pub(crate) fn new(endpoint: String, package_info: wire::application::PackageInfo) -> Self {
+        Self {
+            endpoint,
+            resource: Resource {
+                attributes: vec![
+                    key_value("service.name", string_value(package_info.name)),
+                    key_value("service.version", string_value(package_info.version)),
+                ],
+                dropped_attributes_count: 0,
+            },
+            metadata: HashMap::new(),
+            open_spans: HashMap::new(),
+            requests: HashMap::new(),
+            last_log_at: 0,
+        }
+    }
+
+    /// Registers the exporter with the aggregator and returns the task sending the batches
+    /// to the collector, which has to be spawned on the devtools runtime.
+    ///
+    /// The exporter is never dropped by the aggregator, `buffer_capacity` is the number of
+    /// updates it may fall behind before it is sent the full state again.
+    pub(crate) fn register(
+        self,
+        commands: &mpsc::Sender<Command>,
+        buffer_capacity: usize,
+    ) -> Option<impl Future<Output = ()> + Send + 'static> {
+        let endpoint = match Endpoint::from_shared(self.endpoint.clone()) {
+            Ok(endpoint) => endpoint.timeout(Self::EXPORT_TIMEOUT),
+            Err(e) => {
+                eprintln!("invalid OTLP endpoint `{}`: {}", self.endpoint, e);
+                return None;
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(buffer_capacity);
+
+        let watch = Watch {
+            tx,
+            interests: Interests::Metadata | Interests::Trace | Interests::Ipc | Interests::Spans,
+            log_filter: LogFilter::default(),
+            peer: None,
+            lossless: true,
+            skipped: 0,
+        };
+        if commands.try_send(Command::Instrument(watch)).is_err() {
+            eprintln!("failed to start exporting to `{}`", self.endpoint);
+            return None;
+        }
+
+        Some(self.run(endpoint, rx))
+    }
+
+    async fn run(
+        mut self,
+        endpoint: Endpoint,
+        mut rx: mpsc::Receiver<Result<wire::instrument::Update, tonic::Status>>,
+    ) {
+        // the collector may be started after the app, so connect on the first export
+        let channel = endpoint.connect_lazy();
+        let mut collector = Collector {
+            endpoint: self.endpoint.clone(),
+            resource: self.resource.clone(),
+            traces: TraceServiceClient::new(channel.clone()),
+            logs: LogsServiceClient::new(channel),
+            failing: false,
+        };
+
+        // updates are received while batches are exported, so a slow collector doesn't make
+        // the exporter fall behind the aggregator
+        let (batches_tx, mut batches_rx) = mpsc::channel(Self::MAX_QUEUED_BATCHES);
+
+        let convert = async move {
+            let mut dropped = 0;
+
+            while let Some(Ok(update)) = rx.recv().await {
+                if update.skipped_updates > 0 {
+                    eprintln!(
+                        "the OTLP exporter fell behind, {} updates were skipped",
+                        update.skipped_updates
+                    );
+                }
+
+                let batch = self.convert(update);
+                if batch.spans.is_empty() && batch.log_records.is_empty() {
+                    continue;
+                }
+
+                match batches_tx.try_send(batch) {
+                    Ok(()) if dropped > 0 => {
+                        eprintln!(
+                            "dropped {} batches because the collector at `{}` didn't keep up",
+                            dropped, self.endpoint
+                        );
+                        dropped = 0;
+                    }
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => dropped += 1,
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+        };
+
+        let export = async move {
+            while let Some(batch) = batches_rx.recv().await {
+                collector.export(batch).await;
+            }
+        };
+
+        tokio::join!(convert, export);
+    }
+
+    /// Converts an update into the spans closed and the log events emitted since the last update.
+    ///
+    /// After falling behind, the update contains the full state instead. Spans and requests
+    /// are then only added if they aren't tracked already, spans that were closed in the
+    /// meantime are forgotten and log events that were already exported are skipped.
+    fn convert(&mut self, update: wire::instrument::Update) -> Batch {
+        let catching_up = update.skipped_updates > 0;
+
+        if let Some(new_metadata) = update.new_metadata {
+            for new in new_metadata.metadata {
+                if let (Some(id), Some(metadata)) = (new.id, new.metadata) {
+                    self.metadata.insert(id.id, metadata);
+                }
+            }
+        }
+
+        if let Some(ipc_update) = update.ipc_update {
+            for request in ipc_update.new_requests {
+                if let Some(id) = request.id.as_ref().map(|id| id.id) {
+                    self.requests.entry(id).or_insert(Request {
+                        request,
+                        stats: None,
+                    });
+                }
+            }
+
+            for (id, stats) in ipc_update.stats_update {
+                if let Some(request) = self.requests.get_mut(&id) {
+                    request.stats = Some(stats);
+                }
+            }
+        }
+
+        let span_events: Vec<_> = update
+            .span_update
+            .into_iter()
+            .flat_map(|u| u.span_events)
+            .collect();
+
+        if catching_up {
+            let still_open: HashSet<_> = span_events
+                .iter()
+                .filter_map(|event| match &event.event {
+                    Some(span_event::Event::NewSpan(new_span)) => new_span.id.as_ref(),
+                    _ => None,
+                })
+                .map(|id| id.id)
+                .collect();
+            self.open_spans.retain(|id, _| still_open.contains(id));
+        }
+
+        let mut spans = Vec::new();
+        // events of this update may have been emitted in spans that are closed by it
+        let mut closed = HashMap::new();
+
+        for event in span_events {
+            let at = event.at.as_ref().map_or(0, unix_nanos);
+
+            match event.event {
+                Some(span_event::Event::NewSpan(new_span)) => {
+                    let Some(id) = new_span.id else {
+                        continue;
+                    };
+                    if self.open_spans.contains_key(&id.id) {
+                        continue;
+                    }
+
+                    let open_span =
+                        self.open(new_span.parent, new_span.metadata_id, new_span.fields, at);
+                    self.open_spans.insert(id.id, open_span);
+
+                    if self.open_spans.len() > Self::MAX_OPEN_SPANS {
+                        self.evict_open_spans();
+                    }
+                }
+                Some(span_event::Event::Record(record)) => {
+                    let Some(OpenSpan::Span(span)) =
+                        record.span.and_then(|id| self.open_spans.get_mut(&id.id))
+                    else {
+                        continue;
+                    };
+
+                    for field in record.fields {
+                        match span
+                            .fields
+                            .iter_mut()
+                            .find(|existing| existing.name == field.name)
+                        {
+                            Some(existing) => *existing = field,
+                            None => span.fields.push(field),
+                        }
+                    }
+                }
+                Some(span_event::Event::FollowsFrom(follows_from)) => {
+                    let Some(follows) = follows_from.follows.and_then(|id| self.context(id.id)) else {
+                        continue;
+                    };
+
+                    if let Some(OpenSpan::Span(span)) = follows_from
+                        .span
+                        .and_then(|id| self.open_spans.get_mut(&id.id))
+                    {
+                        span.links.push(follows);
+                    }
+                }
+                Some(span_event::Event::CloseSpan(close)) => {
+                    let Some(id) = close.span else {
+                        continue;
+                    };
+
+                    let request = self.requests.remove(&id.id);
+                    match self.open_spans.remove(&id.id) {
+                        Some(OpenSpan::Span(span)) => {
+                            closed.insert(id.id, span.context);
+                            self.finish(span, request, at, &mut spans);
+                        }
+                        Some(OpenSpan::Phase { context, .. }) => {
+                            closed.insert(id.id, context);
+                        }
+                        None => {}
+                    }
+                }
+                Some(span_event::Event::EnterSpan(_) | span_event::Event::ExitSpan(_)) | None => {}
+            }
+        }
+
+        // requests of evicted spans, or whose span was never received, can't be exported anymore
+        let open_spans = &self.open_spans;
+        self.requests.retain(|id, _| open_spans.contains_key(id));
+
+        let last_log_at = self.last_log_at;
+        let log_records: Vec<_> = update
+            .log_update
+            .into_iter()
+            .flat_map(|u| u.new_events)
+            .filter(|event| !catching_up || event.at.as_ref().map_or(0, unix_nanos) > last_log_at)
+            .filter_map(|event| {
+                // the innermost span the event was emitted in
+                let context = event.parents.first().and_then(|parent| {
+                    self.context(parent.id)
+                        .or_else(|| closed.get(&parent.id).copied())
+                });
+
+                self.log_record(event, context)
+            })
+            .collect();
+
+        if let Some(last) = log_records.iter().map(|record| record.time_unix_nano).max() {
+            self.last_log_at = self.last_log_at.max(last);
+        }
+
+        Batch { spans, log_records }
+    }
+
+    /// Forgets the oldest quarter of the open spans.
+    fn evict_open_spans(&mut self) {
+        let mut open_spans: Vec<_> = self
+            .open_spans
+            .iter()
+            .map(|(id, open_span)| (open_span.start_time_unix_nano(), *id))
+            .collect();
+
+        let evicted = open_spans.len() / 4;
+        open_spans.select_nth_unstable_by_key(evicted, |(at, _)| *at);
+
+        for (_, id) in open_spans.drain(..evicted) {
+            self.open_spans.remove(&id);
+        }
+    }
+
+    fn open(
+        &mut self,
+        parent: Option<wire::SpanId>,
+        metadata_id: Option<wire::MetaId>,
+        fields: Vec<wire::Field>,
+        at: u64,
+    ) -> OpenSpan {
+        let parent_id = parent.map(|parent| parent.id);
+        let metadata_id = metadata_id.map(|id| id.id);
+
+        let phase = metadata_id
+            .and_then(|id| self.metadata.get(&id))
+            .and_then(|metadata| Phase::from_span_name(&metadata.name));
+
+        // phases are only exported for spans of known requests, anything else is a regular span
+        if let (Some(phase), Some(parent_id)) = (phase, parent_id) {
+            if self.requests.contains_key(&parent_id) {
+                if let Some(OpenSpan::Span(request_span)) = self.open_spans.get_mut(&parent_id) {
+                    return OpenSpan::Phase {
+                        context: SpanContext {
+                            trace_id: request_span.context.trace_id,
+                            span_id: request_span.phase_id(phase),
+                        },
+                        start_time_unix_nano: at,
+                    };
+                }
+            }
+        }
+
+        let parent = parent_id.and_then(|id| self.context(id));
+
+        OpenSpan::Span(PendingSpan {
+            context: SpanContext {
+                // every root span starts a new trace
+                trace_id: parent.map_or_else(trace_id, |parent| parent.trace_id),
+                span_id: span_id(),
+            },
+            parent_span_id: parent.map(|parent| parent.span_id),
+            metadata_id,
+            fields,
+            start_time_unix_nano: at,
+            links: Vec::new(),
+            phase_ids: [None; 3],
+        })
+    }
+
+    fn context(&self, id: u64) -> Option<SpanContext> {
+        match self.open_spans.get(&id)? {
+            OpenSpan::Span(span) => Some(span.context),
+            OpenSpan::Phase { context, .. } => Some(*context),
+        }
+    }
+
+    /// Converts a closed span, adding the phase spans if it is the span of an IPC request.
+    fn finish(
+        &self,
+        mut span: PendingSpan,
+        request: Option<Request>,
+        end_time_unix_nano: u64,
+        spans: &mut Vec<Span>,
+    ) {
+        let metadata = span.metadata_id.and_then(|id| self.metadata.get(&id));
+
+        let mut attributes = Vec::new();
+        if let Some(metadata) = metadata {
+            attributes.extend(metadata_attributes(metadata));
+            attributes.extend(field_attributes(&span.fields, metadata));
+        }
+
+        let mut otlp_span = Span {
+            trace_id: span.context.trace_id.to_vec(),
+            span_id: span.context.span_id.to_vec(),
+            trace_state: String::new(),
+            parent_span_id: span.parent_span_id.map(Vec::from).unwrap_or_default(),
+            name: metadata
+                .map(|metadata| metadata.name.clone())
+                .unwrap_or_default(),
+            kind: span::SpanKind::Internal as i32,
+            start_time_unix_nano: span.start_time_unix_nano,
+            end_time_unix_nano,
+            attributes,
+            dropped_attributes_count: 0,
+            events: Vec::new(),
+            dropped_events_count: 0,
+            links: span
+                .links
+                .iter()
+                .map(|follows| span::Link {
+                    trace_id: follows.trace_id.to_vec(),
+                    span_id: follows.span_id.to_vec(),
+                    trace_state: String::new(),
+                    attributes: Vec::new(),
+                    dropped_attributes_count: 0,
+                })
+                .collect(),
+            dropped_links_count: 0,
+            status: None,
+        };
+
+        let Some(Request { request, stats }) = request else {
+            spans.push(otlp_span);
+            return;
+        };
+
+        otlp_span.name = request.cmd.clone();
+        otlp_span.kind = span::SpanKind::Server as i32;
+
+        // the location of the command handler is more useful than the one inside of Tauri
+        if let Some(handler) = &request.handler {
+            otlp_span
+                .attributes
+                .retain(|attribute| !attribute.key.starts_with("code."));
+            otlp_span.attributes.extend(location_attributes(handler));
+        }
+
+        otlp_span
+            .attributes
+            .push(key_value("tauri.ipc.command", string_value(request.cmd)));
+        let kind = wire::ipc::request::Kind::from_i32(request.kind)
+            .unwrap_or(wire::ipc::request::Kind::Sync);
+        otlp_span.attributes.push(key_value(
+            "tauri.ipc.kind",
+            string_value(kind.as_str_name().to_lowercase()),
+        ));
+        if let Some(window) = request.window {
+            otlp_span
+                .attributes
+                .push(key_value("tauri.window", string_value(window)));
+        }
+
+        let Some(stats) = stats else {
+            spans.push(otlp_span);
+            return;
+        };
+
+        if let Some(outcome) = &stats.outcome {
+            use wire::ipc::outcome::Status as Outcome;
+
+            let outcome_status = Outcome::from_i32(outcome.status).unwrap_or(Outcome::Pending);
+            otlp_span.attributes.push(key_value(
+                "tauri.ipc.outcome",
+                string_value(outcome_status.as_str_name().to_lowercase()),
+            ));
+
+            if matches!(outcome_status, Outcome::Error | Outcome::Panicked) {
+                otlp_span.status = Some(Status {
+                    message: outcome.error.clone(),
+                    code: status::StatusCode::Error as i32,
+                });
+            }
+        }
+
+        let phases = [
+            (Phase::DeserializeRequest, &stats.deserialize_request),
+            (Phase::Handler, &stats.inner),
+            (Phase::SerializeResponse, &stats.serialize_reponse),
+        ];
+
+        for (phase, timestamps) in phases {
+            let Some(timestamps) = timestamps else {
+                continue;
+            };
+            // phases that were never entered or never completed have no meaningful duration
+            let (Some(first_enter), Some(last_enter_ended)) =
+                (&timestamps.first_enter, &timestamps.last_enter_ended)
+            else {
+                continue;
+            };
+
+            let mut attributes = Vec::new();
+            if let Some(busy_time) = &timestamps.busy_time {
+                attributes.push(key_value("busy_ns", int_value(duration_nanos(busy_time))));
+            }
+            if let Some(waiting_time) = &timestamps.waiting_time {
+                attributes.push(key_value(
+                    "waiting_ns",
+                    int_value(duration_nanos(waiting_time)),
+                ));
+            }
+
+            spans.push(Span {
+                trace_id: span.context.trace_id.to_vec(),
+                span_id: span.phase_id(phase).to_vec(),
+                trace_state: String::new(),
+                parent_span_id: span.context.span_id.to_vec(),
+                name: phase.name().to_string(),
+                kind: span::SpanKind::Internal as i32,
+                start_time_unix_nano: unix_nanos(first_enter),
+                end_time_unix_nano: unix_nanos(last_enter_ended),
+                attributes,
+                dropped_attributes_count: 0,
+                events: Vec::new(),
+                dropped_events_count: 0,
+                links: Vec::new(),
+                dropped_links_count: 0,
+                status: None,
+            });
+        }
+
+        spans.push(otlp_span);
+    }
+
+    fn log_record(
+        &self,
+        event: wire::log::Event,
+        context: Option<SpanContext>,
+    ) -> Option<LogRecord> {
+        let metadata = self.metadata.get(&event.metadata_id?.id)?;
+
+        let level =
+            wire::metadata::Level::from_i32(metadata.level).unwrap_or(wire::metadata::Level::Trace);
+        let severity_number = match level {
+            wire::metadata::Level::Error => SeverityNumber::Error,
+            wire::metadata::Level::Warn => SeverityNumber::Warn,
+            wire::metadata::Level::Info => SeverityNumber::Info,
+            wire::metadata::Level::Debug => SeverityNumber::Debug,
+            wire::metadata::Level::Trace => SeverityNumber::Trace,
+        };
+
+        // the message is the body of the record, all other fields become attributes
+        let (message, fields): (Vec<_>, Vec<_>) = event
+            .fields
+            .into_iter()
+            .partition(|field| field.name(metadata) == Some("message"));
+
+        let mut attributes = metadata_attributes(metadata);
+        attributes.extend(field_attributes(&fields, metadata));
+
+        let at = event.at.as_ref().map_or(0, unix_nanos);
+
+        Some(LogRecord {
+            time_unix_nano: at,
+            observed_time_unix_nano: at,
+            severity_number: severity_number as i32,
+            severity_text: level.as_str_name().to_string(),
+            body: message
+                .into_iter()
+                .find_map(|field| field.value)
+                .map(|value| field_value(&value)),
+            attributes,
+            dropped_attributes_count: 0,
+            flags: 0,
+            trace_id: context
+                .map(|context| context.trace_id.to_vec())
+                .unwrap_or_default(),
+            span_id: context
+                .map(|context| context.span_id.to_vec())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl Collector {
+    /// Exports a batch, failed batches are dropped since retrying would only make the queue grow.
+    async fn export(&mut self, batch: Batch) {
+        if !batch.spans.is_empty() {
+            let request = ExportTraceServiceRequest {
+                resource_spans: vec![ResourceSpans {
+                    resource: Some(self.resource.clone()),
+                    scope_spans: vec![ScopeSpans {
+                        scope: Some(scope()),
+                        spans: batch.spans,
+                        schema_url: String::new(),
+                    }],
+                    schema_url: String::new(),
+                }],
+            };
+            let result = self.traces.export(request).await;
+            self.report(result.map(drop));
+        }
+
+        if !batch.log_records.is_empty() {
+            let request = ExportLogsServiceRequest {
+                resource_logs: vec![ResourceLogs {
+                    resource: Some(self.resource.clone()),
+                    scope_logs: vec![ScopeLogs {
+                        scope: Some(scope()),
+                        log_records: batch.log_records,
+                        schema_url: String::new(),
+                    }],
+                    schema_url: String::new(),
+                }],
+            };
+            let result = self.logs.export(request).await;
+            self.report(result.map(drop));
+        }
+    }
+
+    fn report(&mut self, result: Result<(), tonic::Status>) {
+        match result {
+            Ok(()) => self.failing = false,
+            Err(e) if !self.failing => {
+                eprintln!("failed to export to `{}`: {}", self.endpoint, e);
+                self.failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+impl OpenSpan {
+    fn start_time_unix_nano(&self) -> u64 {
+        match self {
+            Self::Span(span) => span.start_time_unix_nano,
+            Self::Phase {
+                start_time_unix_nano,
+                ..
+            } => *start_time_unix_nano,
+        }
+    }
+}
+
+impl PendingSpan {
+    fn phase_id(&mut self, phase: Phase) -> [u8; 8] {
+        *self.phase_ids[phase as usize].get_or_insert_with(span_id)
+    }
+}
+
+impl Phase {
+    /// Returns the phase tracked by an `ipc.request.*` span.
+    fn from_span_name(name: &str) -> Option<Self> {
+        match name {
+            "ipc.request.deserialize_arg" => Some(Self::DeserializeRequest),
+            "ipc.request.handler" => Some(Self::Handler),
+            "ipc.request.serialize_returns" => Some(Self::SerializeResponse),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::DeserializeRequest => "deserialize_request",
+            Self::Handler => "handler",
+            Self::SerializeResponse => "serialize_response",
+        }
+    }
+}
+
+fn scope() -> InstrumentationScope {
+    InstrumentationScope {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        attributes: Vec::new(),
+        dropped_attributes_count: 0,
+    }
+}
+
+fn metadata_attributes(metadata: &wire::Metadata) -> Vec<KeyValue> {
+    let level =
+        wire::metadata::Level::from_i32(metadata.level).unwrap_or(wire::metadata::Level::Trace);
+
+    let mut attributes = vec![
+        key_value("target", string_value(metadata.target.clone())),
+        key_value("level", string_value(level.as_str_name().to_string())),
+    ];
+    if let Some(location) = &metadata.location {
+        attributes.extend(location_attributes(location));
+    }
+
+    attributes
+}
+
+/// Converts a location using the OpenTelemetry semantic conventions for source code attributes.
+fn location_attributes(location: &wire::Location) -> Vec<KeyValue> {
+    let mut attributes = Vec::new();
+
+    if let Some(file) = &location.file {
+        attributes.push(key_value("code.filepath", string_value(file.clone())));
+    }
+    if let Some(module_path) = &location.module_path {
+        attributes.push(key_value(
+            "code.namespace",
+            string_value(module_path.clone()),
+        ));
+    }
+    if let Some(line) = location.line {
+        attributes.push(key_value("code.lineno", int_value(line.into())));
+    }
+    if let Some(column) = location.column {
+        attributes.push(key_value("code.column", int_value(column.into())));
+    }
+
+    attributes
+}
+
+fn field_attributes(fields: &[wire::Field], metadata: &wire::Metadata) -> Vec<KeyValue> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let name = field.name(metadata)?;
+            Some(key_value(name, field_value(field.value.as_ref()?)))
+        })
+        .collect()
+}
+
+fn field_value(value: &wire::field::Value) -> AnyValue {
+    use wire::field::Value;
+
+    match value {
+        Value::DebugVal(v) | Value::StrVal(v) => string_value(v.clone()),
+        Value::U64Val(v) => u64_value(*v),
+        Value::I64Val(v) => int_value(*v),
+        Value::BoolVal(v) => AnyValue {
+            value: Some(any_value::Value::BoolValue(*v)),
+        },
+        Value::F64Val(v) => AnyValue {
+            value: Some(any_value::Value::DoubleValue(*v)),
+        },
+        // OTLP integers are 64 bit, so these are sent as strings to keep them precise
+        Value::U128Val(v) => string_value(u128::from(v.clone()).to_string()),
+        Value::I128Val(v) => string_value(i128::from(v.clone()).to_string()),
+        Value::StructuredVal(v) => v.into(),
+    }
+}
+
+fn key_value(key: &str, value: AnyValue) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(value),
+    }
+}
+
+fn string_value(value: String) -> AnyValue {
+    AnyValue {
+        value: Some(any_value::Value::StringValue(value)),
+    }
+}
+
+fn int_value(value: i64) -> AnyValue {
+    AnyValue {
+        value: Some(any_value::Value::IntValue(value)),
+    }
+}
+
+fn u64_value(value: u64) -> AnyValue {
+    match i64::try_from(value) {
+        Ok(value) => int_value(value),
+        Err(_) => string_value(value.to_string()),
+    }
+}
+
+fn unix_nanos(timestamp: &prost_types::Timestamp) -> u64 {
+    let seconds = u64::try_from(timestamp.seconds).unwrap_or_default();
+    let nanos = u64::try_from(timestamp.nanos).unwrap_or_default();
+
+    seconds * 1_000_000_000 + nanos
+}
+
+fn duration_nanos(duration: &prost_types::Duration) -> i64 {
+    duration.seconds * 1_000_000_000 + i64::from(duration.nanos)
+}
+
+fn trace_id() -> [u8; 16] {
+    let mut trace_id = [0; 16];
+    trace_id[..8].copy_from_slice(&next_id().to_be_bytes());
+    trace_id[8..].copy_from_slice(&next_id().to_be_bytes());
+    trace_id
+}
+
+fn span_id() -> [u8; 8] {
+    next_id().to_be_bytes()
+}
+
+/// Returns a unique non-zero ID, collectors reject spans with all-zero IDs.
+///
+/// The IDs are a counter starting at a random seed, scrambled with the SplitMix64 finalizer.
+/// The counter only repeats after 2^64 IDs and the finalizer maps every value to a different ID.
+fn next_id() -> u64 {
+    const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    static COUNTER: OnceLock<AtomicU64> = OnceLock::new();
+    let counter = COUNTER.get_or_init(|| {
+        // the seed hashes the time and process ID with the randomly keyed `RandomState`
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(since_epoch) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(since_epoch.as_nanos());
+        }
+        hasher.write_u32(process::id());
+        AtomicU64::new(hasher.finish())
+    });
+
+    loop {
+        let mut z = counter
+            .fetch_add(GOLDEN_GAMMA, Ordering::Relaxed)
+            .wrapping_add(GOLDEN_GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        if z != 0 {
+            return z;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::wrappers::TcpListenerStream;
+    use wire::otlp::collector::{
+        logs::v1::{logs_service_server, ExportLogsServiceResponse},
+        trace::v1::{trace_service_server, ExportTraceServiceResponse},
+    };
+
+    /// Collects the exported requests.
+    #[derive(Clone, Default)]
+    struct Stub {
+        traces: Arc<Mutex<Vec<ExportTraceServiceRequest>>>,
+        logs: Arc<Mutex<Vec<ExportLogsServiceRequest>>>,
+    }
+
+    #[tonic::async_trait]
+    impl trace_service_server::TraceService for Stub {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportTraceServiceRequest>,
+        ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+            self.traces.lock().unwrap().push(request.into_inner());
+            Ok(tonic::Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    #[tonic::async_trait]
+    impl logs_service_server::LogsService for Stub {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportLogsServiceRequest>,
+        ) -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
+            self.logs.lock().unwrap().push(request.into_inner());
+            Ok(tonic::Response::new(ExportLogsServiceResponse::default()))
+        }
+    }
+
+    fn metadata(
+        id: u64,
+        name: &str,
+        kind: i32,
+        field_names: &[&str],
+    ) -> wire::register_metadata::NewMetadata {
+        wire::register_metadata::NewMetadata {
+            id: Some(wire::MetaId { id }),
+            metadata: Some(wire::Metadata {
+                name: name.to_string(),
+                target: "app".to_string(),
+                location: Some(wire::Location {
+                    file: Some("src/main.rs".to_string()),
+                    module_path: Some("app".to_string()),
+                    line: Some(3),
+                    column: None,
+                }),
+                kind,
+                level: wire::metadata::Level::Info as i32,
+                field_names: field_names.iter().map(|name| name.to_string()).collect(),
+            }),
+        }
+    }
+
+    fn span_event(at: SystemTime, event: span_event::Event) -> wire::spans::SpanEvent {
+        wire::spans::SpanEvent {
+            at: Some(at.into()),
+            event: Some(event),
+        }
+    }
+
+    fn new_span(id: u64, parent: Option<u64>, metadata_id: u64) -> span_event::Event {
+        span_event::Event::NewSpan(span_event::Span {
+            id: Some(wire::SpanId { id }),
+            parent: parent.map(|id| wire::SpanId { id }),
+            metadata_id: Some(wire::MetaId { id: metadata_id }),
+            fields: Vec::new(),
+        })
+    }
+
+    fn close_span(id: u64) -> span_event::Event {
+        span_event::Event::CloseSpan(span_event::Close {
+            span: Some(wire::SpanId { id }),
+        })
+    }
+
+    fn timestamps(
+        first_enter: SystemTime,
+        last_enter_ended: SystemTime,
+    ) -> Option<wire::ipc::Timestamps> {
+        Some(wire::ipc::Timestamps {
+            first_enter: Some(first_enter.into()),
+            last_enter_ended: Some(last_enter_ended.into()),
+            busy_time: Some(prost_types::Duration {
+                seconds: 0,
+                nanos: 2_000_000,
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// An update with a failed IPC request, a span inside of its handler and an event in that span.
+    fn update(start: SystemTime) -> wire::instrument::Update {
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let stats = wire::ipc::Stats {
+            initiated_at: Some(at(0).into()),
+            completed_at: Some(at(10).into()),
+            deserialize_request: timestamps(at(1), at(2)),
+            inner: timestamps(at(3), at(7)),
+            serialize_reponse: timestamps(at(8), at(9)),
+            task_stats: None,
+            outcome: Some(wire::ipc::Outcome {
+                status: wire::ipc::outcome::Status::Error as i32,
+                error: "boom".to_string(),
+            }),
+        };
+
+        wire::instrument::Update {
+            new_metadata: Some(wire::RegisterMetadata {
+                metadata: vec![
+                    metadata(1, "ipc.request", 0, &[]),
+                    metadata(2, "ipc.request.handler", 0, &[]),
+                    metadata(3, "db.query", 0, &[]),
+                    metadata(4, "event", 1, &["message", "rows"]),
+                ],
+                dropped_events: 0,
+            }),
+            ipc_update: Some(wire::ipc::IpcUpdate {
+                new_requests: vec![wire::ipc::Request {
+                    id: Some(wire::Id { id: 10 }),
+                    cmd: "greet".to_string(),
+                    kind: wire::ipc::request::Kind::Async as i32,
+                    metadata: Some(wire::MetaId { id: 1 }),
+                    fields: Vec::new(),
+                    handler: Some(wire::Location {
+                        file: Some("src/commands.rs".to_string()),
+                        module_path: Some("app::commands".to_string()),
+                        line: Some(42),
+                        column: None,
+                    }),
+                    window: Some("main".to_string()),
+                }],
+                stats_update: HashMap::from([(10, stats)]),
+                ..Default::default()
+            }),
+            span_update: Some(wire::spans::SpanUpdate {
+                span_events: vec![
+                    span_event(at(0), new_span(10, None, 1)),
+                    span_event(at(3), new_span(11, Some(10), 2)),
+                    span_event(at(4), new_span(12, Some(11), 3)),
+                    span_event(at(6), close_span(12)),
+                    span_event(at(7), close_span(11)),
+                    span_event(at(10), close_span(10)),
+                ],
+                dropped_events: 0,
+            }),
+            log_update: Some(wire::log::LogUpdate {
+                new_events: vec![wire::log::Event {
+                    metadata_id: Some(wire::MetaId { id: 4 }),
+                    fields: vec![
+                        wire::Field {
+                            name: Some(wire::field::Name::NameIdx(0)),
+                            value: Some(wire::field::Value::DebugVal("querying".to_string())),
+                            metadata_id: None,
+                        },
+                        wire::Field {
+                            name: Some(wire::field::Name::NameIdx(1)),
+                            value: Some(wire::field::Value::U64Val(3)),
+                            metadata_id: None,
+                        },
+                    ],
+                    at: Some(at(5).into()),
+                    parents: vec![wire::SpanId { id: 12 }, wire::SpanId { id: 11 }],
+                    ipc_request: Some(wire::Id { id: 10 }),
+                }],
+                dropped_events: 0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn attribute<'a>(attributes: &'a [KeyValue], key: &str) -> Option<&'a any_value::Value> {
+        attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .and_then(|attribute| attribute.value.as_ref()?.value.as_ref())
+    }
+
+    fn find<'a>(spans: &'a [Span], name: &str) -> &'a Span {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no `{name}` span"))
+    }
+
+    #[tokio::test]
+    async fn exports_requests_spans_and_logs() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = Stub::default();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(trace_service_server::TraceServiceServer::new(stub.clone()))
+                .add_service(logs_service_server::LogsServiceServer::new(stub.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let (commands, mut commands_rx) = mpsc::channel(1);
+        let exporter = Exporter::new(
+            format!("http://{addr}"),
+            wire::application::PackageInfo {
+                name: "app".to_string(),
+                version: "1.0.0".to_string(),
+                ..Default::default()
+            },
+        );
+        tokio::spawn(exporter.register(&commands, 4).unwrap());
+
+        let Some(Command::Instrument(watch)) = commands_rx.recv().await else {
+            panic!("the exporter didn't register");
+        };
+        assert!(watch.lossless);
+        watch.tx.send(Ok(update(SystemTime::now()))).await.unwrap();
+
+        let (traces, logs) = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let traces = stub.traces.lock().unwrap().clone();
+                let logs = stub.logs.lock().unwrap().clone();
+                if !traces.is_empty() && !logs.is_empty() {
+                    break (traces, logs);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("nothing was exported");
+
+        let resource_spans = &traces[0].resource_spans[0];
+        assert_eq!(
+            attribute(
+                &resource_spans.resource.as_ref().unwrap().attributes,
+                "service.name"
+            ),
+            Some(&any_value::Value::StringValue("app".to_string()))
+        );
+        let spans = &resource_spans.scope_spans[0].spans;
+        assert_eq!(spans.len(), 5);
+
+        let request = find(spans, "greet");
+        assert_eq!(request.trace_id.len(), 16);
+        assert_eq!(request.span_id.len(), 8);
+        assert_ne!(request.trace_id, vec![0; 16]);
+        assert!(request.parent_span_id.is_empty());
+        assert_eq!(request.kind, span::SpanKind::Server as i32);
+        let status = request.status.as_ref().unwrap();
+        assert_eq!(status.code, status::StatusCode::Error as i32);
+        assert_eq!(status.message, "boom");
+
+        // the handler location replaces the location of the request span
+        assert_eq!(
+            attribute(&request.attributes, "code.filepath"),
+            Some(&any_value::Value::StringValue(
+                "src/commands.rs".to_string()
+            ))
+        );
+        assert_eq!(
+            attribute(&request.attributes, "code.lineno"),
+            Some(&any_value::Value::IntValue(42))
+        );
+        assert_eq!(
+            request
+                .attributes
+                .iter()
+                .filter(|attribute| attribute.key == "code.filepath")
+                .count(),
+            1
+        );
+
+        for name in ["deserialize_request", "handler", "serialize_response"] {
+            let phase = find(spans, name);
+            assert_eq!(phase.trace_id, request.trace_id);
+            assert_eq!(phase.parent_span_id, request.span_id);
+            assert_ne!(phase.span_id, request.span_id);
+            assert_eq!(
+                attribute(&phase.attributes, "busy_ns"),
+                Some(&any_value::Value::IntValue(2_000_000))
+            );
+        }
+
+        // spans inside of a phase are children of the phase span
+        let handler = find(spans, "handler");
+        let query = find(spans, "db.query");
+        assert_eq!(query.trace_id, request.trace_id);
+        assert_eq!(query.parent_span_id, handler.span_id);
+
+        let log_records = &logs[0].resource_logs[0].scope_logs[0].log_records;
+        assert_eq!(log_records.len(), 1);
+        let record = &log_records[0];
+        assert_eq!(record.trace_id, request.trace_id);
+        assert_eq!(record.span_id, query.span_id);
+        assert_eq!(
+            record.body.as_ref().and_then(|body| body.value.as_ref()),
+            Some(&any_value::Value::StringValue("querying".to_string()))
+        );
+        assert_eq!(
+            attribute(&record.attributes, "rows"),
+            Some(&any_value::Value::IntValue(3))
+        );
+    }
+
+    #[test]
+    fn evicts_spans_that_are_never_closed() {
+        let mut exporter = Exporter::new(String::new(), Default::default());
+        let start = SystemTime::now();
+
+        let mut update = update(start);
+        // the request and handler spans are never closed
+        update
+            .span_update
+            .as_mut()
+            .unwrap()
+            .span_events
+            .retain(|event| !matches!(&event.event, Some(span_event::Event::CloseSpan(close)) if close.span.as_ref().unwrap().id != 12));
+        exporter.convert(update);
+        assert_eq!(exporter.open_spans.len(), 2);
+        assert_eq!(exporter.requests.len(), 1);
+
+        let span_events = (0..Exporter::MAX_OPEN_SPANS as u64)
+            .map(|id| span_event(start + Duration::from_secs(1), new_span(100 + id, None, 3)))
+            .collect();
+        exporter.convert(wire::instrument::Update {
+            span_update: Some(wire::spans::SpanUpdate {
+                span_events,
+                dropped_events: 0,
+            }),
+            ..Default::default()
+        });
+
+        assert!(exporter.open_spans.len() <= Exporter::MAX_OPEN_SPANS);
+        assert!(!exporter.open_spans.contains_key(&10));
+        assert!(exporter.requests.is_empty());
+    }
+
+    #[test]
+    fn catching_up_skips_exported_state() {
+        let mut exporter = Exporter::new(String::new(), Default::default());
+        let start = SystemTime::now();
+
+        let batch = exporter.convert(update(start));
+        assert_eq!(batch.log_records.len(), 1);
+
+        // after falling behind, the log history is sent again
+        let mut full_state = update(start);
+        full_state.skipped_updates = 3;
+        full_state.span_update = None;
+        let batch = exporter.convert(full_state);
+        assert!(batch.log_records.is_empty());
+    }
+
+    #[test]
+    fn ids_are_unique_and_not_zero() {
+        let ids: HashSet<_> = (0..10_000).map(|_| next_id()).collect();
+        assert_eq!(ids.len(), 10_000);
+        assert!(!ids.contains(&0));
+    }
+}
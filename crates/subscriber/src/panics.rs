@@ -0,0 +1,149 @@
+use crate::{Event, Shared};
+use std::{
+    any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    panic,
+    sync::Arc,
+    thread,
+    time::Instant,
+};
+use tokio::sync::mpsc;
+use tracing_subscriber::{registry::LookupSpan, Registry};
+
+/// Installs a panic hook that sends every panic to the aggregator.
+///
+/// The previously installed hook is called afterwards, so panics are still printed as usual.
+pub(crate) fn install_hook(shared: Arc<Shared>, tx: mpsc::Sender<Event>) {
+    let previous = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        report(info.payload(), info.location(), &shared, &tx);
+        previous(info);
+    }));
+}
+
+/// Sends a panic with the given payload to the aggregator.
+fn report(
+    payload: &(dyn Any + Send),
+    location: Option<&panic::Location<'_>>,
+    shared: &Shared,
+    tx: &mpsc::Sender<Event>,
+) {
+    let event = Event::Panic {
+        message: message(payload),
+        location: location.map(Into::into),
+        thread: thread::current().name().map(ToString::to_string),
+        backtrace: backtrace(),
+        spans: current_spans(),
+        at: Instant::now(),
+    };
+
+    if tx.try_send(event).is_ok() {
+        // the process might be about to exit, so don't wait for the buffer to fill up
+        shared.flush.trigger();
+    } else {
+        shared.dropped_panic_events.record(1);
+    }
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Captures a backtrace if they are enabled through `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`,
+/// like the default panic hook does, and an empty string otherwise.
+///
+/// Capturing a backtrace is slow, so apps that panic frequently, e.g. in `catch_unwind`, don't
+/// pay for it unless they opted in.
+fn backtrace() -> String {
+    let backtrace = Backtrace::capture();
+    match backtrace.status() {
+        BacktraceStatus::Captured => backtrace.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Returns the spans the current thread is in, innermost first.
+///
+/// The span stack is only available when the global subscriber is built on top of a `Registry`,
+/// otherwise this only contains the current span.
+fn current_spans() -> Vec<wire::SpanId> {
+    tracing::dispatcher::get_default(|dispatch| {
+        let Some(current) = dispatch.current_span().id().cloned() else {
+            return Vec::new();
+        };
+
+        let Some(registry) = dispatch.downcast_ref::<Registry>() else {
+            return vec![current.into()];
+        };
+
+        registry
+            .span(&current)
+            .map(|span| span.scope().map(|span| span.id().into()).collect())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_messages() {
+        assert_eq!(message(&"static"), "static");
+        assert_eq!(message(&"owned".to_string()), "owned");
+        assert_eq!(message(&42), "Box<dyn Any>");
+    }
+
+    #[test]
+    fn reports_panics() {
+        let shared = Shared::default();
+        let (tx, mut rx) = mpsc::channel(1);
+
+        let (outer, inner) = tracing::subscriber::with_default(Registry::default(), || {
+            let outer = tracing::info_span!("outer");
+            let _outer = outer.enter();
+            let inner = tracing::info_span!("inner");
+            let _inner = inner.enter();
+
+            report(&"boom", Some(panic::Location::caller()), &shared, &tx);
+
+            (outer.id().unwrap(), inner.id().unwrap())
+        });
+
+        let Ok(Event::Panic {
+            message,
+            location,
+            thread,
+            spans,
+            ..
+        }) = rx.try_recv()
+        else {
+            panic!("no panic event was sent");
+        };
+        assert_eq!(message, "boom");
+        assert_eq!(location.unwrap().file.as_deref(), Some(file!()));
+        assert!(thread.is_some());
+        assert_eq!(
+            spans,
+            [wire::SpanId::from(inner), wire::SpanId::from(outer)]
+        );
+    }
+
+    #[test]
+    fn counts_dropped_panics() {
+        let shared = Shared::default();
+        let (tx, _rx) = mpsc::channel(1);
+
+        report(&"first", None, &shared, &tx);
+        report(&"second", None, &shared, &tx);
+
+        assert_eq!(shared.dropped_panic_events.take_pending(), 1);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::util::matches_pattern;
+use std::{fmt, sync::Arc};
+
+/// Configuration for capturing the arguments and responses of IPC requests.
+///
+/// Payloads may contain sensitive data, so capturing is disabled unless explicitly enabled
+/// through [`Builder::capture_ipc_payloads`](crate::Builder::capture_ipc_payloads).
+#[derive(Debug, Clone)]
+pub(crate) struct PayloadCapture {
+    /// Captured values are truncated to this many bytes.
+    pub(crate) max_bytes: usize,
+    /// Arguments whose name matches one of these patterns are redacted.
+    pub(crate) redact: Vec<String>,
+    /// Decides about redacting values the patterns don't cover.
+    pub(crate) redact_hook: Option<RedactHook>,
+}
+
+/// A user-provided function deciding whether a captured value is redacted.
+///
+/// It's called with the argument name, empty for responses, and the value.
+#[derive(Clone)]
+pub(crate) struct RedactHook(pub(crate) Arc<RedactFn>);
+
+type RedactFn = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+impl PayloadCapture {
+    /// Applies the size limit and redaction patterns to a captured value.
+    ///
+    /// `name` is the argument name, `None` for responses.
+    pub(crate) fn capture(&self, name: Option<String>, value: String) -> wire::ipc::Payload {
+        let len = value.len();
+        let redacted = name
+            .as_deref()
+            .is_some_and(|name| self.redact.iter().any(|pattern| matches_pattern(pattern, name)))
+            || self
+                .redact_hook
+                .as_ref()
+                .is_some_and(|hook| (hook.0)(name.as_deref().unwrap_or_default(), &value));
+
+        let value = if redacted {
+            String::new()
+        } else {
+            truncate(value, self.max_bytes)
+        };
+
+        wire::ipc::Payload {
+            name: name.unwrap_or_default(),
+            truncated: !redacted && value.len() < len,
+            len: len as u64,
+            value,
+            redacted,
+        }
+    }
+}
+
+/// Truncates `value` to at most `max_bytes`, without splitting a character.
+fn truncate(mut value: String, max_bytes: usize) -> String {
+    if value.len() > max_bytes {
+        let mut end = max_bytes;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+
+    value
+}
+
+impl fmt::Debug for RedactHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RedactHook(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(redact: &[&str], redact_hook: Option<fn(&str, &str) -> bool>) -> PayloadCapture {
+        PayloadCapture {
+            max_bytes: 8,
+            redact: redact.iter().map(ToString::to_string).collect(),
+            redact_hook: redact_hook.map(|hook| RedactHook(Arc::new(hook))),
+        }
+    }
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(truncate("hello".to_string(), 8), "hello");
+        assert_eq!(truncate("hello".to_string(), 5), "hello");
+        assert_eq!(truncate("hello".to_string(), 3), "hel");
+        assert_eq!(truncate("hello".to_string(), 0), "");
+        // `é` takes two bytes, `🦀` four
+        assert_eq!(truncate("héllo".to_string(), 2), "h");
+        assert_eq!(truncate("héllo".to_string(), 3), "hé");
+        assert_eq!(truncate("a🦀".to_string(), 4), "a");
+        assert_eq!(truncate("🦀".to_string(), 3), "");
+    }
+
+    #[test]
+    fn truncates_captured_values() {
+        let payload = capture(&[], None).capture(Some("name".into()), "\"abcdefghij\"".into());
+
+        assert_eq!(payload.name, "name");
+        assert_eq!(payload.value, "\"abcdefg");
+        assert_eq!(payload.len, 12);
+        assert!(payload.truncated);
+        assert!(!payload.redacted);
+    }
+
+    #[test]
+    fn redacts_matching_arguments() {
+        let capture = capture(&["*password*"], Some(|_, value| value.contains('@')));
+
+        let payload = capture.capture(Some("newPassword".into()), "\"hunter2\"".into());
+        assert!(payload.redacted);
+        assert!(!payload.truncated);
+        assert_eq!(payload.value, "");
+        assert_eq!(payload.len, 9);
+
+        let payload = capture.capture(Some("email".into()), "\"a@b.c\"".into());
+        assert!(payload.redacted);
+
+        // responses have no name, so only the hook applies to them
+        let payload = capture.capture(None, "\"a@b.c\"".into());
+        assert!(payload.redacted);
+        let payload = capture.capture(None, "\"password\"".into());
+        assert!(!payload.redacted);
+        assert_eq!(payload.value, "\"passwor");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{log_filter::LogFilter, Command, Watch};
+use prost::Message;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    thread,
+    time::SystemTime,
+};
+use tokio::sync::mpsc;
+use wire::instrument::Interests;
+
+/// Records every update the aggregator publishes to a file, so sessions can be analyzed later.
+///
+/// The recorder is registered with the aggregator like any other client, interested in everything.
+/// Updates are written from a separate thread, so slow disks don't hold up the devtools runtime.
+/// Unlike clients, the recorder isn't dropped when it falls behind. It skips updates instead and
+/// catches up with the full state once it has room again.
+pub(crate) struct Recorder {
+    path: PathBuf,
+    max_file_bytes: u64,
+    max_files: u32,
+    header: wire::recording::Header,
+}
+
+/// The file currently being written to.
+struct Part {
+    file: BufWriter<File>,
+    /// The number of bytes written to `file` so far.
+    written: u64,
+}
+
+impl Recorder {
+    /// The default maximum size of a single recording file, before the recording continues
+    /// in a new file.
+    pub(crate) const DEFAULT_MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+    /// The default maximum number of recording files, after which the recording stops.
+    pub(crate) const DEFAULT_MAX_FILES: u32 = 16;
+
+    pub(crate) fn new(
+        path: PathBuf,
+        max_file_bytes: u64,
+        max_files: u32,
+        package_info: wire::application::PackageInfo,
+    ) -> Self {
+        Self {
+            path,
+            max_file_bytes,
+            max_files,
+            header: wire::recording::Header {
+                protocol_version: wire::recording::PROTOCOL_VERSION,
+                package_info: Some(package_info),
+                started_at: Some(SystemTime::now().into()),
+                part: 0,
+            },
+        }
+    }
+
+    /// Registers the recorder with the aggregator and spawns the thread writing the updates.
+    ///
+    /// `buffer_capacity` is the number of updates the recorder may fall behind before the
+    /// aggregator skips updates for it.
+    pub(crate) fn spawn(self, commands: &mpsc::Sender<Command>, buffer_capacity: usize) {
+        let (tx, mut rx) = mpsc::channel(buffer_capacity);
+
+        let watch = Watch {
+            tx,
+            interests: Interests::all(),
+            log_filter: LogFilter::default(),
+            peer: None,
+            lossless: true,
+            skipped: 0,
+        };
+        if commands.try_send(Command::Instrument(watch)).is_err() {
+            eprintln!("failed to start recording to `{}`", self.path.display());
+            return;
+        }
+
+        thread::Builder::new()
+            .name("devtools_recorder".into())
+            .spawn(move || {
+                let mut recorder = self;
+                let mut part = None;
+
+                while let Some(Ok(update)) = rx.blocking_recv() {
+                    // the update itself marks the gap in the recording
+                    if update.skipped_updates > 0 {
+                        eprintln!(
+                            "recording to `{}` fell behind, {} updates were skipped",
+                            recorder.path.display(),
+                            update.skipped_updates
+                        );
+                    }
+
+                    let len = update.encoded_len();
+                    let len = prost::length_delimiter_len(len) + len;
+                    if recorder.needs_new_part(&part, len)
+                        && recorder.header.part + 1 >= recorder.max_files
+                    {
+                        eprintln!(
+                            "stopped recording to `{}` after reaching the maximum of {} files",
+                            recorder.path.display(),
+                            recorder.max_files
+                        );
+                        return;
+                    }
+
+                    if let Err(e) = recorder.write(&mut part, &update) {
+                        eprintln!("failed to record to `{}`: {}", recorder.path.display(), e);
+                        return;
+                    }
+                }
+            })
+            .expect("devtools recorder could not spawn thread");
+    }
+
+    fn write(
+        &mut self,
+        part: &mut Option<Part>,
+        update: &wire::instrument::Update,
+    ) -> io::Result<()> {
+        let update = update.encode_length_delimited_to_vec();
+
+        if self.needs_new_part(part, update.len()) {
+            self.header.part += 1;
+            *part = None;
+        }
+
+        let current = match part {
+            Some(current) => current,
+            None => part.insert(self.create_part()?),
+        };
+
+        current.file.write_all(&update)?;
+        current.written += update.len() as u64;
+
+        // the app may exit at any time, so don't keep updates buffered
+        current.file.flush()
+    }
+
+    /// Whether an encoded update of `len` bytes has to go to a new file.
+    ///
+    /// Every file contains at least one update, so huge updates don't create empty files.
+    fn needs_new_part(&self, part: &Option<Part>, len: usize) -> bool {
+        part.as_ref()
+            .is_some_and(|current| current.written + len as u64 > self.max_file_bytes)
+    }
+
+    fn create_part(&self) -> io::Result<Part> {
+        if self.header.part == 0 {
+            self.remove_stale_parts()?;
+        }
+
+        let path = wire::recording::part_path(&self.path, self.header.part);
+        let mut file = BufWriter::new(File::create(path)?);
+
+        let header = self.header.encode_length_delimited_to_vec();
+        file.write_all(&header)?;
+
+        Ok(Part {
+            file,
+            written: header.len() as u64,
+        })
+    }
+
+    /// Removes the `.1`, `.2` etc. files an earlier recording to the same path left behind, so
+    /// they aren't mistaken for parts of this recording.
+    fn remove_stale_parts(&self) -> io::Result<()> {
+        for part in 1.. {
+            match fs::remove_file(wire::recording::part_path(&self.path, part)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
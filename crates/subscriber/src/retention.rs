@@ -0,0 +1,112 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Limits for how much data about *completed* IPC requests, tasks and Tauri events is kept around.
+///
+/// Completed data is retained so that clients connecting later still receive a window of
+/// recent history, ongoing requests and tasks are never evicted.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Retention {
+    /// How long completed data is kept after completing.
+    pub(crate) max_age: Duration,
+    /// The maximum number of completed entries that are kept.
+    pub(crate) max_count: usize,
+    /// The maximum number of bytes (in their encoded protobuf representation) completed entries may take up.
+    pub(crate) max_bytes: usize,
+}
+
+/// A completed entry that may be evicted.
+pub(crate) struct Completed<K> {
+    pub(crate) key: K,
+    pub(crate) completed_at: Instant,
+    pub(crate) size: usize,
+}
+
+/// Completed entries of one kind, ordered by when they completed.
+pub(crate) struct Completions<K> {
+    entries: VecDeque<Completed<K>>,
+    /// Size of all entries.
+    bytes: usize,
+}
+
+impl<K> Completions<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Adds a completed entry, keeping the entries ordered.
+    ///
+    /// Entries are mostly added in the order they complete, so this rarely has to look further
+    /// than the last entry.
+    pub(crate) fn insert(&mut self, entry: Completed<K>) {
+        let idx = self
+            .entries
+            .iter()
+            .rposition(|other| other.completed_at <= entry.completed_at)
+            .map_or(0, |idx| idx + 1);
+
+        self.bytes += entry.size;
+        self.entries.insert(idx, entry);
+    }
+}
+
+impl Retention {
+    /// Default retention for completed data.
+    pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+    /// Default maximum number of completed entries kept.
+    pub const DEFAULT_MAX_COUNT: usize = 10_000;
+
+    /// Default maximum number of bytes completed entries may take up.
+    pub const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+    /// Removes the entries that should be evicted from `completed` and returns their keys.
+    ///
+    /// Entries older than `max_age` are always evicted, after that the oldest entries are evicted
+    /// until both the count and size limits are satisfied. Eviction stops at the first entry
+    /// `keep` returns `true` for.
+    pub(crate) fn evict<K>(
+        &self,
+        completed: &mut Completions<K>,
+        now: Instant,
+        mut keep: impl FnMut(&K) -> bool,
+    ) -> Vec<K> {
+        let mut evicted = Vec::new();
+
+        while let Some(oldest) = completed.entries.front() {
+            let expired = now.saturating_duration_since(oldest.completed_at) > self.max_age;
+            if !expired
+                && completed.entries.len() <= self.max_count
+                && completed.bytes <= self.max_bytes
+            {
+                break;
+            }
+
+            if keep(&oldest.key) {
+                break;
+            }
+
+            completed.bytes -= oldest.size;
+            if let Some(oldest) = completed.entries.pop_front() {
+                evicted.push(oldest.key);
+            }
+        }
+
+        evicted
+    }
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_age: Self::DEFAULT_MAX_AGE,
+            max_count: Self::DEFAULT_MAX_COUNT,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+        }
+    }
+}
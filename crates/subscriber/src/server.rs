@@ -1,16 +1,13 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
 
 use wire::instrument::Interests;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tracing_subscriber::filter;
 
-use crate::{Command, Watch};
-
-/// Default maximum capacity for the channel of events sent from a
-/// [`Server`] to each subscribed client.
-///
-/// When this capacity is exhausted, the client is assumed to be inactive,
-/// and may be disconnected.
-const DEFAULT_CLIENT_BUFFER_CAPACITY: usize = 1024 * 4;
+use crate::{app_info::AppInfo, filters::Filters, log_filter::LogFilter, Command, Shared, Watch};
 
 pub struct Server {
     addr: SocketAddr,
@@ -20,10 +17,15 @@ pub struct Server {
 
 struct InstrumentServer {
     tx: mpsc::Sender<Command>,
+    shared: Arc<Shared>,
+    filters: Filters,
+    client_buffer_capacity: usize,
+    event_buffer_capacity: usize,
 }
 
 struct ApplicationServer {
-    package_info: tauri::PackageInfo,
+    tx: mpsc::Sender<Command>,
+    app_info: AppInfo,
 }
 
 impl Server {
@@ -31,11 +33,35 @@ impl Server {
 
     pub const DEFAULT_PORT: u16 = 6669;
 
-    pub(crate) fn new(tx: mpsc::Sender<Command>, package_info: tauri::PackageInfo) -> Self {
+    /// Default maximum capacity for the channel of events sent from a
+    /// [`Server`] to each subscribed client.
+    ///
+    /// When this capacity is exhausted, the client is assumed to be inactive,
+    /// and may be disconnected.
+    pub const DEFAULT_CLIENT_BUFFER_CAPACITY: usize = 1024 * 4;
+
+    pub(crate) fn new(
+        tx: mpsc::Sender<Command>,
+        shared: Arc<Shared>,
+        filters: Filters,
+        app_info: AppInfo,
+        addr: SocketAddr,
+        client_buffer_capacity: usize,
+        event_buffer_capacity: usize,
+    ) -> Self {
         Self {
-            addr: SocketAddr::new(Self::DEFAULT_IP, Self::DEFAULT_PORT),
-            instrument: InstrumentServer { tx },
-            application: ApplicationServer { package_info },
+            addr,
+            application: ApplicationServer {
+                tx: tx.clone(),
+                app_info,
+            },
+            instrument: InstrumentServer {
+                tx,
+                shared,
+                filters,
+                client_buffer_capacity,
+                event_buffer_capacity,
+            },
         }
     }
 
@@ -63,7 +89,8 @@ impl wire::instrument::instrument_server::Instrument for InstrumentServer {
         &self,
         req: tonic::Request<wire::instrument::InstrumentRequest>,
     ) -> Result<tonic::Response<Self::WatchUpdatesStream>, tonic::Status> {
-        match req.remote_addr() {
+        let peer = req.remote_addr();
+        match peer {
             Some(addr) => tracing::debug!(client.addr = %addr, "starting a new watch"),
             None => tracing::debug!(client.addr = %"<unknown>", "starting a new watch"),
         }
@@ -74,12 +101,26 @@ impl wire::instrument::instrument_server::Instrument for InstrumentServer {
         })?;
 
         // create output channel and send tx to the aggregator for tracking
-        let (tx, rx) = mpsc::channel(DEFAULT_CLIENT_BUFFER_CAPACITY);
+        let (tx, rx) = mpsc::channel(self.client_buffer_capacity);
 
-        let interests = Interests::from_bits(req.into_inner().interests)
+        let req = req.into_inner();
+
+        let interests = req
+            .interests()
             .ok_or(tonic::Status::invalid_argument("could not parse sources"))?;
 
-        permit.send(Command::Instrument(Watch { tx, interests }));
+        let log_filter = req.log_filter.parse::<LogFilter>().map_err(|e| {
+            tonic::Status::invalid_argument(format!("could not parse log filter: {e}"))
+        })?;
+
+        permit.send(Command::Instrument(Watch {
+            tx,
+            interests,
+            log_filter,
+            peer,
+            lossless: false,
+            skipped: 0,
+        }));
 
         tracing::debug!("watch started");
 
@@ -89,9 +130,124 @@ impl wire::instrument::instrument_server::Instrument for InstrumentServer {
 
     async fn update_interests(
         &self,
-        _req: tonic::Request<wire::instrument::UpdateInterestsRequest>,
+        req: tonic::Request<wire::instrument::UpdateInterestsRequest>,
     ) -> Result<tonic::Response<wire::instrument::UpdateInterestsResponse>, tonic::Status> {
-        todo!()
+        // watches are identified by the connection they were opened over
+        let peer = req.remote_addr().ok_or(tonic::Status::failed_precondition(
+            "cannot update interests, client address is unknown",
+        ))?;
+
+        let interests = Interests::from_bits(req.into_inner().interests)
+            .ok_or(tonic::Status::invalid_argument("could not parse interests"))?;
+
+        // reserve capacity to message the aggregator
+        let permit = self.tx.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot update interests, aggregation task is not running")
+        })?;
+
+        let (tx, rx) = oneshot::channel();
+        permit.send(Command::UpdateInterests {
+            peer,
+            interests,
+            updated: tx,
+        });
+
+        let updated = rx.await.map_err(|_| {
+            tonic::Status::internal("cannot update interests, aggregation task is not running")
+        })?;
+
+        if updated == 0 {
+            return Err(tonic::Status::not_found(
+                "no active watch found for this client",
+            ));
+        }
+
+        tracing::debug!(client.addr = %peer, ?interests, updated, "interests updated");
+
+        Ok(tonic::Response::new(
+            wire::instrument::UpdateInterestsResponse {},
+        ))
+    }
+
+    async fn get_diagnostics(
+        &self,
+        _req: tonic::Request<wire::instrument::GetDiagnosticsRequest>,
+    ) -> Result<tonic::Response<wire::instrument::Diagnostics>, tonic::Status> {
+        let logs = self.shared.dropped_log_events.total();
+        let metadata = self.shared.dropped_metadata_events.total();
+        let ipc = self.shared.dropped_ipc_events.total();
+        let tasks = self.shared.dropped_task_events.total();
+        let spans = self.shared.dropped_span_events.total();
+        let events = self.shared.dropped_tauri_events.total();
+        let windows = self.shared.dropped_window_events.total();
+        let panics = self.shared.dropped_panic_events.total();
+
+        let diagnostics = wire::instrument::Diagnostics {
+            event_buffer_capacity: self.event_buffer_capacity as u64,
+            dropped_events: Some(wire::instrument::DroppedEvents {
+                logs,
+                metadata,
+                ipc,
+                tasks,
+                spans,
+                events,
+                windows,
+                panics,
+                total: logs + metadata + ipc + tasks + spans + events + windows + panics,
+            }),
+        };
+
+        Ok(tonic::Response::new(diagnostics))
+    }
+
+    async fn set_filter(
+        &self,
+        req: tonic::Request<wire::instrument::SetFilterRequest>,
+    ) -> Result<tonic::Response<wire::instrument::SetFilterResponse>, tonic::Status> {
+        let req = req.into_inner();
+
+        // parse both filters first, so an invalid request doesn't change anything
+        let invalid_filter = |e: filter::ParseError| {
+            tonic::Status::invalid_argument(format!("could not parse filter: {e}"))
+        };
+        let layer_filter = req
+            .layer_filter
+            .map(|directives| directives.parse::<filter::Targets>())
+            .transpose()
+            .map_err(invalid_filter)?;
+        let fmt_filter = req
+            .fmt_filter
+            .map(|directives| directives.parse::<filter::Targets>())
+            .transpose()
+            .map_err(invalid_filter)?;
+
+        let reload_failed =
+            |e| tonic::Status::internal(format!("cannot set filter, subscriber is gone: {e}"));
+
+        if let Some(layer_filter) = layer_filter {
+            self.filters
+                .set_layer_filter(layer_filter)
+                .map_err(reload_failed)?;
+        }
+
+        if let Some(fmt_filter) = fmt_filter {
+            self.filters
+                .set_fmt_filter(fmt_filter)
+                .map_err(reload_failed)?;
+        }
+
+        let res = wire::instrument::SetFilterResponse {
+            layer_filter: self.filters.layer_filter().unwrap_or_default(),
+            fmt_filter: self.filters.fmt_filter().unwrap_or_default(),
+        };
+
+        tracing::debug!(
+            layer_filter = %res.layer_filter,
+            fmt_filter = %res.fmt_filter,
+            "filters updated"
+        );
+
+        Ok(tonic::Response::new(res))
     }
 }
 
@@ -101,13 +257,67 @@ impl wire::application::application_server::Application for ApplicationServer {
         &self,
         _req: tonic::Request<wire::application::GetPackageInfoRequest>,
     ) -> Result<tonic::Response<wire::application::PackageInfo>, tonic::Status> {
-        let info = wire::application::PackageInfo {
-            name: self.package_info.name.clone(),
-            version: self.package_info.version.to_string(),
-            authors: self.package_info.authors.to_string(),
-            description: self.package_info.description.to_string(),
-        };
+        Ok(tonic::Response::new(self.app_info.package_info()))
+    }
+
+    async fn list_windows(
+        &self,
+        _req: tonic::Request<wire::application::ListWindowsRequest>,
+    ) -> Result<tonic::Response<wire::application::ListWindowsResponse>, tonic::Status> {
+        // reserve capacity to message the aggregator
+        let permit = self.tx.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot list windows, aggregation task is not running")
+        })?;
+
+        let (tx, rx) = oneshot::channel();
+        permit.send(Command::ListWindows { windows: tx });
+
+        let windows = rx.await.map_err(|_| {
+            tonic::Status::internal("cannot list windows, aggregation task is not running")
+        })?;
+
+        Ok(tonic::Response::new(wire::application::ListWindowsResponse {
+            windows,
+        }))
+    }
 
-        Ok(tonic::Response::new(info))
+    async fn get_config(
+        &self,
+        _req: tonic::Request<wire::application::GetConfigRequest>,
+    ) -> Result<tonic::Response<wire::application::Config>, tonic::Status> {
+        Ok(tonic::Response::new(self.app_info.config()))
+    }
+
+    async fn get_plugins(
+        &self,
+        _req: tonic::Request<wire::application::GetPluginsRequest>,
+    ) -> Result<tonic::Response<wire::application::Plugins>, tonic::Status> {
+        // reserve capacity to message the aggregator
+        let permit = self.tx.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot list plugins, aggregation task is not running")
+        })?;
+
+        let (tx, rx) = oneshot::channel();
+        permit.send(Command::ListCommands { commands: tx });
+
+        let commands = rx.await.map_err(|_| {
+            tonic::Status::internal("cannot list plugins, aggregation task is not running")
+        })?;
+
+        Ok(tonic::Response::new(self.app_info.plugins(commands)))
+    }
+
+    async fn get_versions(
+        &self,
+        _req: tonic::Request<wire::application::GetVersionsRequest>,
+    ) -> Result<tonic::Response<wire::application::Versions>, tonic::Status> {
+        Ok(tonic::Response::new(self.app_info.versions()))
+    }
+
+    async fn get_process_info(
+        &self,
+        _req: tonic::Request<wire::application::GetProcessInfoRequest>,
+    ) -> Result<tonic::Response<wire::application::ProcessInfo>, tonic::Status> {
+        Ok(tonic::Response::new(self.app_info.process_info()))
     }
 }
@@ -1,11 +1,14 @@
-use crate::{ToProto, Unsent};
+use crate::{ToProto, Unsent, WakeOp};
 use hdrhistogram::{
     self,
     serialization::{Serializer, V2Serializer},
 };
 use parking_lot::Mutex;
 use std::{
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock, Weak,
+    },
     time::{Duration, Instant},
 };
 
@@ -19,6 +22,126 @@ pub(crate) struct IPCRequestStats {
     deserialize_request: Mutex<Timestamps>,
     serialize_response: Mutex<Timestamps>,
     inner: Mutex<Timestamps>,
+
+    /// Whether the request handler is async and therefore runs on its own task.
+    is_async: bool,
+    /// Stats of the task the async request handler runs on.
+    task_stats: OnceLock<Arc<TaskStats>>,
+
+    outcome: Mutex<Outcome>,
+}
+
+/// Statistics aggregated over all completed requests of a command.
+///
+/// Every request records its total busy time of each phase once. The durations only cover the
+/// requests completed in the last one to two [`CommandStats::WINDOW`]s.
+#[derive(Debug)]
+pub(crate) struct CommandStats {
+    is_dirty: AtomicBool,
+
+    calls: u64,
+    errors: u64,
+
+    deserialize_request: WindowedHistogram,
+    serialize_response: WindowedHistogram,
+    inner: WindowedHistogram,
+}
+
+/// A histogram of the values recorded in the last one to two windows.
+///
+/// Values are recorded into `current`, which replaces `previous` once it is a window old.
+#[derive(Debug)]
+struct WindowedHistogram {
+    current: Histogram,
+    previous: Histogram,
+    window: Duration,
+    started_at: Instant,
+}
+
+/// How an IPC request ended.
+#[derive(Debug, Clone, Default, PartialEq)]
+enum Outcome {
+    #[default]
+    Pending,
+    Ok,
+    Error(String),
+    Panicked,
+    Cancelled,
+}
+
+/// Performance statistics of a spawned async task.
+#[derive(Debug)]
+pub(crate) struct TaskStats {
+    is_dirty: AtomicBool,
+    is_dropped: AtomicBool,
+
+    created_at: Instant,
+    dropped_at: Mutex<Option<Instant>>,
+
+    wakes: AtomicUsize,
+    waker_clones: AtomicUsize,
+    waker_drops: AtomicUsize,
+    self_wakes: AtomicUsize,
+
+    poll_stats: PollStats,
+
+    /// The IPC request this task is handling, if any. Changes to the task's stats
+    /// mark the request's stats as dirty too.
+    ipc_request: OnceLock<Weak<IPCRequestStats>>,
+}
+
+/// Statistics of a resource (e.g. a timer or semaphore) created by the async runtime.
+#[derive(Debug)]
+pub(crate) struct ResourceStats {
+    is_dirty: AtomicBool,
+
+    created_at: Instant,
+    dropped_at: Mutex<Option<Instant>>,
+
+    /// The latest value of every attribute reported through state updates.
+    attributes: Mutex<Vec<wire::Field>>,
+}
+
+/// How a state update changes the previous value of a resource attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum AttributeOp {
+    Add,
+    Sub,
+    #[default]
+    Override,
+}
+
+/// Statistics of an async operation on a resource, e.g. waiting for a timer.
+#[derive(Debug)]
+pub(crate) struct AsyncOpStats {
+    is_dirty: AtomicBool,
+
+    created_at: Instant,
+    dropped_at: Mutex<Option<Instant>>,
+
+    poll_stats: PollStats,
+}
+
+#[derive(Debug)]
+struct PollStats {
+    /// The number of polls in progress.
+    ///
+    /// Tasks are only polled by one thread at a time, but a task's span may be
+    /// entered recursively.
+    current_polls: AtomicUsize,
+    /// The total number of polls.
+    polls: AtomicUsize,
+    timestamps: Mutex<PollTimestamps>,
+}
+
+#[derive(Debug)]
+struct PollTimestamps {
+    first_poll: Option<Instant>,
+    last_wake: Option<Instant>,
+    last_poll_started: Option<Instant>,
+    last_poll_ended: Option<Instant>,
+    busy_time: Duration,
+    scheduled_time: Duration,
 }
 
 #[derive(Debug)]
@@ -45,12 +168,14 @@ impl IPCRequestStats {
         busy_duration_max: u64,
         waiting_duration_max: u64,
         initiated_at: Instant,
+        kind: wire::ipc::request::Kind,
     ) -> Self {
         Self {
             is_dirty: AtomicBool::new(true),
+            is_async: kind == wire::ipc::request::Kind::Async,
+            task_stats: OnceLock::new(),
             initiated_at,
             completed_at: Mutex::new(None),
-            // task_stats: TaskStats::new(poll_duration_max, scheduled_duration_max, initiated_at),
             deserialize_request: Mutex::new(Timestamps::new(
                 busy_duration_max,
                 waiting_duration_max,
@@ -60,6 +185,7 @@ impl IPCRequestStats {
                 waiting_duration_max,
             )),
             inner: Mutex::new(Timestamps::new(busy_duration_max, waiting_duration_max)),
+            outcome: Mutex::new(Outcome::Pending),
         }
     }
 
@@ -93,11 +219,67 @@ impl IPCRequestStats {
         self.make_dirty();
     }
 
+    /// Marks the request as completed.
+    ///
+    /// Requests that neither failed nor panicked succeeded if their response was serialized,
+    /// otherwise they were cancelled.
     pub(crate) fn complete(&self, at: Instant) {
         *self.completed_at.lock() = Some(at);
+
+        let mut outcome = self.outcome.lock();
+        if *outcome == Outcome::Pending {
+            *outcome = if self.serialize_response.lock().first_enter.is_some() {
+                Outcome::Ok
+            } else {
+                Outcome::Cancelled
+            };
+        }
+        drop(outcome);
+
+        self.make_dirty();
+    }
+
+    /// Returns `true` if the request handler returned an error or panicked.
+    pub(crate) fn has_failed(&self) -> bool {
+        matches!(*self.outcome.lock(), Outcome::Error(_) | Outcome::Panicked)
+    }
+
+    /// Records the error returned by the request handler.
+    pub(crate) fn fail(&self, error: String) {
+        let mut outcome = self.outcome.lock();
+        // a panic while handling an error takes precedence
+        if *outcome != Outcome::Panicked {
+            *outcome = Outcome::Error(error);
+        }
+        drop(outcome);
+
+        self.make_dirty();
+    }
+
+    /// Records that the request handler panicked.
+    pub(crate) fn panic(&self) {
+        *self.outcome.lock() = Outcome::Panicked;
         self.make_dirty();
     }
 
+    pub(crate) fn completed_at(&self) -> Option<Instant> {
+        *self.completed_at.lock()
+    }
+
+    /// Associates the task that runs the request handler with this request.
+    ///
+    /// Only the first task spawned for an async request is recorded, sync requests are ignored.
+    pub(crate) fn set_task_stats(self: &Arc<Self>, task_stats: &Arc<TaskStats>) {
+        if !self.is_async {
+            return;
+        }
+
+        if self.task_stats.set(task_stats.clone()).is_ok() {
+            let _ = task_stats.ipc_request.set(Arc::downgrade(self));
+            self.make_dirty();
+        }
+    }
+
     #[inline]
     fn make_dirty(&self) {
         self.is_dirty.swap(true, Ordering::AcqRel);
@@ -141,7 +323,169 @@ impl ToProto for IPCRequestStats {
             deserialize_request: Some(self.deserialize_request.lock().to_proto(base_time)),
             serialize_reponse: Some(self.serialize_response.lock().to_proto(base_time)),
             inner: Some(self.inner.lock().to_proto(base_time)),
-            task_stats: None,
+            task_stats: self
+                .task_stats
+                .get()
+                .map(|task_stats| task_stats.to_proto(base_time)),
+            outcome: Some(self.outcome.lock().to_proto(base_time)),
+        }
+    }
+}
+
+impl CommandStats {
+    /// The length of the windows the durations are aggregated over.
+    pub(crate) const WINDOW: Duration = Duration::from_secs(60);
+
+    /// Creates empty stats, whose histograms clamp durations to `duration_max`.
+    pub(crate) fn new(duration_max: Duration, now: Instant) -> Self {
+        let duration_max = duration_max.as_nanos() as u64;
+        let histogram = || WindowedHistogram::new(duration_max, Self::WINDOW, now);
+
+        Self {
+            is_dirty: AtomicBool::new(true),
+            calls: 0,
+            errors: 0,
+            deserialize_request: histogram(),
+            serialize_response: histogram(),
+            inner: histogram(),
+        }
+    }
+
+    /// Adds a request that completed at `now` to the aggregate.
+    pub(crate) fn add(&mut self, request: &IPCRequestStats, now: Instant) {
+        self.calls += 1;
+        if request.has_failed() {
+            self.errors += 1;
+        }
+
+        let phases = [
+            (&mut self.deserialize_request, &request.deserialize_request),
+            (&mut self.serialize_response, &request.serialize_response),
+            (&mut self.inner, &request.inner),
+        ];
+        for (histogram, phase) in phases {
+            histogram.rotate(now);
+
+            // phases that never ran, e.g. of a request that failed to deserialize, are skipped
+            let phase = phase.lock();
+            if phase.first_enter.is_some() {
+                histogram.current.record_duration(phase.busy_time);
+            }
+        }
+
+        self.is_dirty.swap(true, Ordering::AcqRel);
+    }
+
+    /// Drops the durations that are older than two windows, marking the stats to be sent again
+    /// if any were dropped.
+    pub(crate) fn rotate(&mut self, now: Instant) {
+        let mut rotated = false;
+        for histogram in [
+            &mut self.deserialize_request,
+            &mut self.serialize_response,
+            &mut self.inner,
+        ] {
+            rotated |= histogram.rotate(now);
+        }
+
+        if rotated {
+            self.is_dirty.swap(true, Ordering::AcqRel);
+        }
+    }
+}
+
+impl WindowedHistogram {
+    fn new(max: u64, window: Duration, now: Instant) -> Self {
+        Self {
+            current: Histogram::new(max),
+            previous: Histogram::new(max),
+            window,
+            started_at: now,
+        }
+    }
+
+    /// Starts a new window if the current one is over. Returns whether any recorded values were
+    /// dropped.
+    fn rotate(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        if elapsed < self.window {
+            return false;
+        }
+
+        let expired = elapsed >= self.window * 2;
+        let dropped = !self.previous.is_empty() || (expired && !self.current.is_empty());
+        if expired {
+            self.previous = self.current.empty();
+            self.current = self.current.empty();
+            self.started_at = now;
+        } else {
+            self.previous = std::mem::replace(&mut self.current, self.previous.empty());
+            self.started_at += self.window;
+        }
+
+        dropped
+    }
+
+    /// Returns the values of both windows combined.
+    fn combined(&self) -> Histogram {
+        let mut histogram = self.current.empty();
+        histogram.merge(&self.current);
+        histogram.merge(&self.previous);
+        histogram
+    }
+}
+
+impl Unsent for CommandStats {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for CommandStats {
+    type Output = wire::ipc::CommandStats;
+
+    fn to_proto(&self, base_time: &crate::util::TimeAnchor) -> Self::Output {
+        let durations = |histogram: &WindowedHistogram| {
+            let histogram = histogram.combined();
+            wire::ipc::Durations {
+                p50: histogram.percentile(50.0),
+                p95: histogram.percentile(95.0),
+                p99: histogram.percentile(99.0),
+                histogram: Some(histogram.to_proto(base_time)),
+            }
+        };
+
+        wire::ipc::CommandStats {
+            calls: self.calls,
+            errors: self.errors,
+            deserialize_request: Some(durations(&self.deserialize_request)),
+            serialize_response: Some(durations(&self.serialize_response)),
+            inner: Some(durations(&self.inner)),
+        }
+    }
+}
+
+impl ToProto for Outcome {
+    type Output = wire::ipc::Outcome;
+
+    fn to_proto(&self, _: &crate::util::TimeAnchor) -> Self::Output {
+        use wire::ipc::outcome::Status;
+
+        let (status, error) = match self {
+            Outcome::Pending => (Status::Pending, String::new()),
+            Outcome::Ok => (Status::Ok, String::new()),
+            Outcome::Error(error) => (Status::Error, error.clone()),
+            Outcome::Panicked => (Status::Panicked, String::new()),
+            Outcome::Cancelled => (Status::Cancelled, String::new()),
+        };
+
+        wire::ipc::Outcome {
+            status: status as i32,
+            error,
         }
     }
 }
@@ -209,129 +553,365 @@ impl ToProto for Timestamps {
     }
 }
 
-// impl TaskStats {
-//     pub(crate) fn new(
-//         poll_duration_max: u64,
-//         scheduled_duration_max: u64,
-//         created_at: Instant,
-//     ) -> Self {
-//         Self {
-//             is_dirty: AtomicBool::new(true),
-//             is_dropped: AtomicBool::new(false),
-//             created_at,
-//             dropped_at: Mutex::new(None),
-//             wakes: AtomicUsize::new(0),
-//             waker_clones: AtomicUsize::new(0),
-//             waker_drops: AtomicUsize::new(0),
-//             self_wakes: AtomicUsize::new(0),
-//             poll_stats: PollStats {
-//                 current_polls: AtomicUsize::new(0),
-//                 polls: AtomicUsize::new(0),
-//                 timestamps: Mutex::new(PollTimestamps {
-//                     first_poll: None,
-//                     last_wake: None,
-//                     last_poll_started: None,
-//                     last_poll_ended: None,
-//                     busy_time: Duration::new(0, 0),
-//                     scheduled_time: Duration::new(0, 0),
-//                     poll_histogram: Histogram::new(poll_duration_max),
-//                     scheduled_histogram: Histogram::new(scheduled_duration_max),
-//                 }),
-//             },
-//         }
-//     }
-
-//     pub(crate) fn record_wake_op(&self, op: crate::WakeOp, at: Instant) {}
-
-//     pub(crate) fn wake(&self, at: Instant, self_wake: bool) {
-//         let mut timestamps = self.poll_stats.timestamps.lock();
-//         timestamps.last_wake = std::cmp::max(timestamps.last_wake, Some(at));
-
-//         self.wakes.fetch_add(1, Ordering::Release);
-//         if self_wake {
-//             self.self_wakes.fetch_add(1, Ordering::Release);
-//         }
-
-//         self.make_dirty();
-//     }
-
-//     pub(crate) fn start_poll(&self, at: Instant) {
-//         if self.poll_stats.current_polls.fetch_add(1, Ordering::AcqRel) > 0 {
-//             return;
-//         }
-
-//         // We are starting the first poll
-//         let mut timestamps = self.poll_stats.timestamps.lock();
-//         if timestamps.first_poll.is_none() {
-//             timestamps.first_poll = Some(at);
-//         }
-
-//         timestamps.last_poll_started = Some(at);
-
-//         self.poll_stats.polls.fetch_add(1, Ordering::Release);
-
-//         // If the last poll ended after the last wake then it was likely
-//         // a self-wake, so we measure from the end of the last poll instead.
-//         // This also ensures that `busy_time` and `scheduled_time` don't overlap.
-//         let scheduled = match std::cmp::max(timestamps.last_wake, timestamps.last_poll_ended) {
-//             Some(scheduled) => scheduled,
-//             None => return, // Async operations record polls, but not wakes
-//         };
-
-//         // `at < scheduled` is possible when a task switches threads between polls.
-//         let elapsed = at.saturating_duration_since(scheduled);
-
-//         // if we have a scheduled time histogram, add the timestamp
-//         timestamps.scheduled_histogram.record_duration(elapsed);
-
-//         timestamps.scheduled_time += elapsed;
-//     }
-
-//     pub(crate) fn end_poll(&self, at: Instant) {
-//         if self.poll_stats.current_polls.fetch_add(1, Ordering::AcqRel) > 0 {
-//             return;
-//         }
-
-//         // We are starting the first poll
-//         let mut timestamps = self.poll_stats.timestamps.lock();
-//         if timestamps.first_poll.is_none() {
-//             timestamps.first_poll = Some(at);
-//         }
-
-//         timestamps.last_poll_started = Some(at);
-
-//         self.poll_stats.polls.fetch_add(1, Ordering::Release);
-
-//         // If the last poll ended after the last wake then it was likely
-//         // a self-wake, so we measure from the end of the last poll instead.
-//         // This also ensures that `busy_time` and `scheduled_time` don't overlap.
-//         let scheduled = match std::cmp::max(timestamps.last_wake, timestamps.last_poll_ended) {
-//             Some(scheduled) => scheduled,
-//             None => return, // Async operations record polls, but not wakes
-//         };
-
-//         // `at < scheduled` is possible when a task switches threads between polls.
-//         let elapsed = at.saturating_duration_since(scheduled);
-
-//         // if we have a scheduled time histogram, add the timestamp
-//         timestamps.scheduled_histogram.record_duration(elapsed);
-
-//         timestamps.scheduled_time += elapsed;
-//     }
-
-//     pub(crate) fn drop_task(&self, at: Instant) {}
-
-//     #[inline]
-//     fn make_dirty(&self) {
-//         self.is_dirty.swap(true, Ordering::AcqRel);
-//     }
-// }
+impl TaskStats {
+    pub(crate) fn new(created_at: Instant) -> Self {
+        Self {
+            is_dirty: AtomicBool::new(true),
+            is_dropped: AtomicBool::new(false),
+            created_at,
+            dropped_at: Mutex::new(None),
+            wakes: AtomicUsize::new(0),
+            waker_clones: AtomicUsize::new(0),
+            waker_drops: AtomicUsize::new(0),
+            self_wakes: AtomicUsize::new(0),
+            poll_stats: PollStats::new(),
+            ipc_request: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn record_wake_op(&self, op: WakeOp, at: Instant) {
+        match op {
+            WakeOp::Wake { self_wake } => {
+                self.waker_drops.fetch_add(1, Ordering::Release);
+                self.wake(at, self_wake);
+            }
+            WakeOp::WakeByRef { self_wake } => {
+                self.wake(at, self_wake);
+            }
+            WakeOp::Clone => {
+                self.waker_clones.fetch_add(1, Ordering::Release);
+            }
+            WakeOp::Drop => {
+                self.waker_drops.fetch_add(1, Ordering::Release);
+            }
+        }
+        self.make_dirty();
+    }
+
+    fn wake(&self, at: Instant, self_wake: bool) {
+        let mut timestamps = self.poll_stats.timestamps.lock();
+        timestamps.last_wake = std::cmp::max(timestamps.last_wake, Some(at));
+
+        self.wakes.fetch_add(1, Ordering::Release);
+        if self_wake {
+            self.self_wakes.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    pub(crate) fn start_poll(&self, at: Instant) {
+        if self.poll_stats.start_poll(at) {
+            self.make_dirty();
+        }
+    }
+
+    pub(crate) fn end_poll(&self, at: Instant) {
+        if self.poll_stats.end_poll(at) {
+            self.make_dirty();
+        }
+    }
+
+    pub(crate) fn dropped_at(&self) -> Option<Instant> {
+        *self.dropped_at.lock()
+    }
+
+    pub(crate) fn drop_task(&self, dropped_at: Instant) {
+        if self.is_dropped.swap(true, Ordering::AcqRel) {
+            // The task was already dropped.
+            return;
+        }
+
+        *self.dropped_at.lock() = Some(dropped_at);
+        self.make_dirty();
+    }
+
+    /// Records that the task panicked, which means the IPC request it handles panicked too.
+    pub(crate) fn panic(&self) {
+        if let Some(ipc_request) = self.ipc_request.get().and_then(Weak::upgrade) {
+            ipc_request.panic();
+        }
+    }
+
+    #[inline]
+    fn make_dirty(&self) {
+        self.is_dirty.swap(true, Ordering::AcqRel);
+
+        if let Some(ipc_request) = self.ipc_request.get().and_then(Weak::upgrade) {
+            ipc_request.make_dirty();
+        }
+    }
+}
+
+impl Unsent for TaskStats {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for TaskStats {
+    type Output = wire::tasks::Stats;
+
+    fn to_proto(&self, base_time: &crate::util::TimeAnchor) -> Self::Output {
+        let poll_stats = self.poll_stats.to_proto(base_time);
+        let timestamps = self.poll_stats.timestamps.lock();
+
+        wire::tasks::Stats {
+            created_at: Some(base_time.to_timestamp(self.created_at)),
+            dropped_at: self
+                .dropped_at
+                .lock()
+                .map(|t: Instant| base_time.to_timestamp(t)),
+            wakes: self.wakes.load(Ordering::Acquire) as u64,
+            waker_clones: self.waker_clones.load(Ordering::Acquire) as u64,
+            waker_drops: self.waker_drops.load(Ordering::Acquire) as u64,
+            self_wakes: self.self_wakes.load(Ordering::Acquire) as u64,
+            last_wake: timestamps.last_wake.map(|t| base_time.to_timestamp(t)),
+            poll_stats: Some(poll_stats),
+            scheduled_time: timestamps.scheduled_time.try_into().ok(),
+        }
+    }
+}
+
+impl PollStats {
+    fn new() -> Self {
+        Self {
+            current_polls: AtomicUsize::new(0),
+            polls: AtomicUsize::new(0),
+            timestamps: Mutex::new(PollTimestamps {
+                first_poll: None,
+                last_wake: None,
+                last_poll_started: None,
+                last_poll_ended: None,
+                busy_time: Duration::ZERO,
+                scheduled_time: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// Returns `true` if the stats changed.
+    fn start_poll(&self, at: Instant) -> bool {
+        if self.current_polls.fetch_add(1, Ordering::AcqRel) > 0 {
+            return false;
+        }
+
+        // We are starting the first poll
+        let mut timestamps = self.timestamps.lock();
+        if timestamps.first_poll.is_none() {
+            timestamps.first_poll = Some(at);
+        }
+
+        timestamps.last_poll_started = Some(at);
+
+        self.polls.fetch_add(1, Ordering::Release);
+
+        // If the last poll ended after the last wake then it was likely
+        // a self-wake, so we measure from the end of the last poll instead.
+        // This also ensures that `busy_time` and `scheduled_time` don't overlap.
+        let scheduled = match std::cmp::max(timestamps.last_wake, timestamps.last_poll_ended) {
+            Some(scheduled) => scheduled,
+            None => return false, // The task hasn't been woken yet, the first poll isn't scheduled
+        };
+
+        // `at < scheduled` is possible when a task switches threads between polls.
+        let elapsed = at.saturating_duration_since(scheduled);
+
+        timestamps.scheduled_time += elapsed;
+
+        true
+    }
+
+    /// Returns `true` if the stats changed.
+    fn end_poll(&self, at: Instant) -> bool {
+        // Are we ending the last current poll?
+        if self.current_polls.fetch_sub(1, Ordering::AcqRel) > 1 {
+            return false;
+        }
+
+        let mut timestamps = self.timestamps.lock();
+        // the span was entered before we started tracking it
+        let Some(started) = timestamps.last_poll_started else {
+            return false;
+        };
+
+        timestamps.last_poll_ended = Some(at);
+        // `at < started` is possible with a skewed clock, the poll is ignored then
+        let Some(elapsed) = at.checked_duration_since(started) else {
+            return false;
+        };
+
+        timestamps.busy_time += elapsed;
+
+        true
+    }
+}
+
+impl ToProto for PollStats {
+    type Output = wire::PollStats;
+
+    fn to_proto(&self, base_time: &crate::util::TimeAnchor) -> Self::Output {
+        let timestamps = self.timestamps.lock();
+
+        wire::PollStats {
+            polls: self.polls.load(Ordering::Acquire) as u64,
+            first_poll: timestamps.first_poll.map(|t| base_time.to_timestamp(t)),
+            last_poll_started: timestamps
+                .last_poll_started
+                .map(|t| base_time.to_timestamp(t)),
+            last_poll_ended: timestamps
+                .last_poll_ended
+                .map(|t| base_time.to_timestamp(t)),
+            busy_time: timestamps.busy_time.try_into().ok(),
+        }
+    }
+}
+
+impl ResourceStats {
+    pub(crate) fn new(created_at: Instant) -> Self {
+        Self {
+            is_dirty: AtomicBool::new(true),
+            created_at,
+            dropped_at: Mutex::new(None),
+            attributes: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn dropped_at(&self) -> Option<Instant> {
+        *self.dropped_at.lock()
+    }
+
+    pub(crate) fn drop_resource(&self, dropped_at: Instant) {
+        self.dropped_at.lock().get_or_insert(dropped_at);
+        self.is_dirty.store(true, Ordering::Release);
+    }
+
+    /// Applies a state update reported by the runtime to the attribute `name`.
+    ///
+    /// Numbers are added or subtracted when the update says so, everything else overrides the
+    /// previous value.
+    pub(crate) fn update_attribute(&self, name: String, value: wire::field::Value, op: AttributeOp) {
+        use wire::field::Value;
+
+        let mut attributes = self.attributes.lock();
+        let name = Some(wire::field::Name::StrName(name));
+        let Some(attribute) = attributes.iter_mut().find(|field| field.name == name) else {
+            attributes.push(wire::Field {
+                name,
+                value: Some(value),
+                metadata_id: None,
+            });
+            self.is_dirty.store(true, Ordering::Release);
+            return;
+        };
+
+        let value = match (op, attribute.value.take(), value) {
+            (AttributeOp::Add, Some(Value::U64Val(prev)), Value::U64Val(v)) => {
+                Value::U64Val(prev.saturating_add(v))
+            }
+            (AttributeOp::Sub, Some(Value::U64Val(prev)), Value::U64Val(v)) => {
+                Value::U64Val(prev.saturating_sub(v))
+            }
+            (AttributeOp::Add, Some(Value::I64Val(prev)), Value::I64Val(v)) => {
+                Value::I64Val(prev.saturating_add(v))
+            }
+            (AttributeOp::Sub, Some(Value::I64Val(prev)), Value::I64Val(v)) => {
+                Value::I64Val(prev.saturating_sub(v))
+            }
+            (_, _, value) => value,
+        };
+
+        attribute.value = Some(value);
+        self.is_dirty.store(true, Ordering::Release);
+    }
+}
+
+impl Unsent for ResourceStats {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for ResourceStats {
+    type Output = wire::tasks::ResourceStats;
+
+    fn to_proto(&self, base_time: &crate::util::TimeAnchor) -> Self::Output {
+        wire::tasks::ResourceStats {
+            created_at: Some(base_time.to_timestamp(self.created_at)),
+            dropped_at: self.dropped_at().map(|t| base_time.to_timestamp(t)),
+            attributes: self.attributes.lock().clone(),
+        }
+    }
+}
+
+impl AsyncOpStats {
+    pub(crate) fn new(created_at: Instant) -> Self {
+        Self {
+            is_dirty: AtomicBool::new(true),
+            created_at,
+            dropped_at: Mutex::new(None),
+            poll_stats: PollStats::new(),
+        }
+    }
+
+    pub(crate) fn dropped_at(&self) -> Option<Instant> {
+        *self.dropped_at.lock()
+    }
+
+    pub(crate) fn drop_async_op(&self, dropped_at: Instant) {
+        self.dropped_at.lock().get_or_insert(dropped_at);
+        self.is_dirty.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn start_poll(&self, at: Instant) {
+        if self.poll_stats.start_poll(at) {
+            self.is_dirty.store(true, Ordering::Release);
+        }
+    }
+
+    pub(crate) fn end_poll(&self, at: Instant) {
+        if self.poll_stats.end_poll(at) {
+            self.is_dirty.store(true, Ordering::Release);
+        }
+    }
+}
+
+impl Unsent for AsyncOpStats {
+    fn take_unsent(&self) -> bool {
+        self.is_dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_unsent(&self) -> bool {
+        self.is_dirty.load(Ordering::Acquire)
+    }
+}
+
+impl ToProto for AsyncOpStats {
+    type Output = wire::tasks::AsyncOpStats;
+
+    fn to_proto(&self, base_time: &crate::util::TimeAnchor) -> Self::Output {
+        wire::tasks::AsyncOpStats {
+            created_at: Some(base_time.to_timestamp(self.created_at)),
+            dropped_at: self.dropped_at().map(|t| base_time.to_timestamp(t)),
+            poll_stats: Some(self.poll_stats.to_proto(base_time)),
+        }
+    }
+}
 
 impl Histogram {
     fn new(max: u64) -> Self {
         // significant figures should be in the [0-5] range and memory usage
         // grows exponentially with higher a sigfig
-        let histogram = hdrhistogram::Histogram::new_with_max(max, 2).unwrap();
+        // the histogram can't track values below 2, which a zero max configured by the user
+        // would otherwise ask for
+        let max = max.max(2);
+        let histogram = hdrhistogram::Histogram::new_with_max(max, 2)
+            .expect("max is at least twice the lowest trackable value");
         Self {
             histogram,
             max,
@@ -340,6 +920,50 @@ impl Histogram {
         }
     }
 
+    /// Creates an empty histogram with the same max value.
+    fn empty(&self) -> Self {
+        Self::new(self.max)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.histogram.is_empty() && self.outliers == 0
+    }
+
+    /// Adds all values recorded in `other`, clamping them to this histogram's max value.
+    fn merge(&mut self, other: &Histogram) {
+        self.outliers += other.outliers;
+        self.max_outlier = std::cmp::max(self.max_outlier, other.max_outlier);
+
+        for value in other.histogram.iter_recorded() {
+            let value_ns = value.value_iterated_to();
+            let count = value.count_at_value();
+            if value_ns > self.max {
+                self.outliers += count;
+                self.max_outlier = std::cmp::max(self.max_outlier, Some(value_ns));
+            }
+            self.histogram.saturating_record_n(value_ns.min(self.max), count);
+        }
+    }
+
+    /// Returns the duration at the given percentile, `None` if the histogram is empty.
+    ///
+    /// Outliers are recorded as the max value, so percentiles that fall among them are
+    /// reported as the highest outlier instead.
+    fn percentile(&self, percentile: f64) -> Option<prost_types::Duration> {
+        if self.histogram.is_empty() {
+            return None;
+        }
+
+        let len = self.histogram.len();
+        let rank = ((percentile / 100.0 * len as f64).ceil() as u64).max(1);
+        let value_ns = match self.max_outlier {
+            Some(max_outlier) if rank > len.saturating_sub(self.outliers) => max_outlier,
+            _ => self.histogram.value_at_percentile(percentile),
+        };
+
+        Duration::from_nanos(value_ns).try_into().ok()
+    }
+
     fn record_duration(&mut self, duration: Duration) {
         let mut duration_ns = duration.as_nanos() as u64;
 
@@ -373,3 +997,44 @@ impl ToProto for Histogram {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aggregator::Aggregator, util::TimeAnchor};
+
+    /// Returns a completed request whose handler was busy for `busy`.
+    fn request(busy: Duration) -> IPCRequestStats {
+        let start = Instant::now();
+        let max = Duration::from_secs(1).as_nanos() as u64;
+        let request = IPCRequestStats::new(max, max, start, wire::ipc::request::Kind::Sync);
+        request.start_inner(start);
+        request.end_inner(start + busy);
+        request.complete(start + busy);
+        request
+    }
+
+    fn handler_p50(stats: &CommandStats) -> Duration {
+        let inner = stats.to_proto(&TimeAnchor::new()).inner.unwrap();
+        inner.p50.unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn records_commands_slower_than_the_poll_duration_max() {
+        let now = Instant::now();
+        let mut stats = CommandStats::new(Aggregator::DEFAULT_COMMAND_DURATION_MAX, now);
+        stats.add(&request(Duration::from_secs(5)), now);
+
+        let p50 = handler_p50(&stats);
+        assert!(p50 >= Duration::from_millis(4_950) && p50 <= Duration::from_millis(5_050));
+    }
+
+    #[test]
+    fn reports_percentiles_among_outliers_as_the_highest_outlier() {
+        let now = Instant::now();
+        let mut stats = CommandStats::new(Duration::from_secs(1), now);
+        stats.add(&request(Duration::from_secs(5)), now);
+
+        assert_eq!(handler_p50(&stats), Duration::from_secs(5));
+    }
+}
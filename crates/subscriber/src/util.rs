@@ -38,4 +38,67 @@ impl TimeAnchor {
     pub fn to_timestamp(&self, t: Instant) -> prost_types::Timestamp {
         self.to_system_time(t).into()
     }
-}
\ No newline at end of file
+}
+
+/// Matches `name` against a case-insensitive pattern where `*` matches any sequence of characters.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+
+    let mut parts = pattern.split('*');
+    // `split` always yields at least one part
+    let first = parts.next().unwrap();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            // the last part has to match the end of the name
+            return rest.ends_with(part);
+        }
+
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    // no `*` in the pattern, so it has to match exactly
+    rest.is_empty()
+}
+#[cfg(test)]
+mod tests {
+    use super::matches_pattern;
+
+    #[test]
+    fn matches_exact_names() {
+        assert!(matches_pattern("token", "token"));
+        assert!(matches_pattern("token", "TOKEN"));
+        assert!(matches_pattern("TOKEN", "token"));
+        assert!(!matches_pattern("token", "tokens"));
+        assert!(!matches_pattern("token", "a_token"));
+        assert!(!matches_pattern("", "token"));
+        assert!(matches_pattern("", ""));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("*key*", "API_KEY"));
+        assert!(matches_pattern("*key*", "keychain"));
+        assert!(matches_pattern("*key*", "key"));
+        assert!(!matches_pattern("*key*", "kex"));
+        assert!(matches_pattern("api_*", "api_secret"));
+        assert!(!matches_pattern("api_*", "my_api_secret"));
+        assert!(matches_pattern("*_secret", "api_secret"));
+        assert!(!matches_pattern("*_secret", "api_secret_2"));
+        assert!(matches_pattern("a*b*c", "aXbYc"));
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(!matches_pattern("a*b*c", "acb"));
+        // the suffix must not overlap the prefix
+        assert!(!matches_pattern("ab*ba", "aba"));
+    }
+}
@@ -1,44 +1,195 @@
+use crate::{stats::AttributeOp, WakeOp};
 use tracing_core::field::Visit;
 
+/// Implements the listed `Visit` methods by forwarding them to the visitor's `field_visitor`.
+macro_rules! forward_to_field_visitor {
+    ($($(#[$attr:meta])* $method:ident($ty:ty)),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            fn $method(&mut self, field: &tracing_core::Field, value: $ty) {
+                self.field_visitor.$method(field, value)
+            }
+        )*
+    };
+}
+
+/// Records the fields of a span or event with their typed values.
+///
+/// Field names are sent as indices into the metadata's field names, so they don't have to be
+/// repeated for every span and event.
 pub struct FieldVisitor {
     fields: Vec<wire::Field>,
-    meta_id: wire::MetaId,
+    metadata: &'static tracing_core::Metadata<'static>,
 }
 
 pub struct IPCVisitor {
     field_visitor: FieldVisitor,
     cmd: Option<String>,
     kind: Option<wire::ipc::request::Kind>,
+    window: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+pub struct TaskVisitor {
+    field_visitor: FieldVisitor,
+    kind: wire::tasks::task::Kind,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+pub struct TaskVisitorResult {
+    pub fields: Vec<wire::Field>,
+    pub kind: wire::tasks::task::Kind,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// Extracts the kind, type and location of a resource from a `runtime.resource` span.
+pub struct ResourceVisitor {
+    field_visitor: FieldVisitor,
+    kind: Option<String>,
+    concrete_type: Option<String>,
+    is_internal: bool,
+    file: Option<String>,
     line: Option<u32>,
     column: Option<u32>,
 }
 
+pub struct ResourceVisitorResult {
+    pub fields: Vec<wire::Field>,
+    pub kind: String,
+    pub concrete_type: String,
+    pub is_internal: bool,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// Extracts the method that started an async operation from a `runtime.resource.async_op` span.
+#[derive(Default)]
+pub struct AsyncOpVisitor {
+    source: Option<String>,
+}
+
+/// Collects the attribute updates of a `runtime::resource::state_update` event.
+///
+/// Every field is an attribute, except for `<name>.op` fields which say how the attribute
+/// `<name>` changes.
+#[derive(Default)]
+pub struct StateUpdateVisitor {
+    values: Vec<(String, wire::field::Value)>,
+    ops: Vec<(String, AttributeOp)>,
+}
+
+/// Extracts the event name and windows from the spans tauri emits while emitting and
+/// delivering events.
+pub struct EventVisitor {
+    field_visitor: FieldVisitor,
+    name: Option<String>,
+    source_window: Option<String>,
+    target_window: Option<String>,
+    window: Option<String>,
+    payload_len: u64,
+}
+
+pub struct EventVisitorResult {
+    pub fields: Vec<wire::Field>,
+    /// The event name, only set for `event.emit` spans.
+    pub name: Option<String>,
+    pub source_window: Option<String>,
+    pub target_window: Option<String>,
+    /// The window of the listener, only set for `event.deliver` spans.
+    pub window: Option<String>,
+    pub payload_len: u64,
+}
+
+/// Extracts the window label and the changed state from the window events tauri emits.
+#[derive(Default)]
+pub struct WindowVisitor {
+    window: Option<String>,
+    url: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    focused: Option<bool>,
+}
+
+/// Separates the captured value from the other fields of the spans tauri emits while
+/// deserializing an argument or serializing the response of an IPC request.
+///
+/// The argument name is read from the `arg` field and the value from the `value` field, all
+/// other fields are kept as regular span fields.
+pub struct PayloadVisitor {
+    field_visitor: FieldVisitor,
+    name: Option<String>,
+    value: Option<String>,
+}
+
+pub struct PayloadVisitorResult {
+    /// All fields except the value.
+    pub fields: Vec<wire::Field>,
+    pub name: Option<String>,
+    pub value: Option<String>,
+}
+
+/// Extracts the `error` field tauri records on the spans of an IPC request whose handler
+/// returned an `Err`.
+#[derive(Default)]
+pub struct ErrorVisitor {
+    error: Option<String>,
+}
+
+/// Extracts the waker operation and the task it applies to from a waker event.
+#[derive(Default)]
+pub struct WakerVisitor {
+    id: Option<tracing_core::span::Id>,
+    op: Option<WakeOp>,
+}
+
 pub struct IPCVisitorResult {
     pub fields: Vec<wire::Field>,
     pub cmd: String,
     pub kind: wire::ipc::request::Kind,
+    pub window: Option<String>,
     pub line: Option<u32>,
     pub column: Option<u32>,
 }
 
 impl FieldVisitor {
-    pub(crate) fn new(meta_id: wire::MetaId) -> Self {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
         FieldVisitor {
             fields: Vec::default(),
-            meta_id,
+            metadata,
         }
     }
     pub(crate) fn result(self) -> Vec<wire::Field> {
         self.fields
     }
+
+    fn push(&mut self, field: &tracing_core::Field, value: wire::field::Value) {
+        let name = match self.metadata.fields().iter().position(|f| f == *field) {
+            Some(idx) => wire::field::Name::NameIdx(idx as u64),
+            // the field belongs to a different callsite, this shouldn't happen
+            None => field.name().into(),
+        };
+
+        self.fields.push(wire::Field {
+            metadata_id: Some(self.metadata.into()),
+            name: Some(name),
+            value: Some(value),
+        })
+    }
 }
 
 impl IPCVisitor {
-    pub(crate) fn new(meta_id: wire::MetaId) -> Self {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
         IPCVisitor {
-            field_visitor: FieldVisitor::new(meta_id),
+            field_visitor: FieldVisitor::new(metadata),
             cmd: None,
             kind: None,
+            window: None,
             line: None,
             column: None
         }
@@ -51,6 +202,7 @@ impl IPCVisitor {
                 fields,
                 cmd,
                 kind,
+                window: self.window,
                 line: self.line,
                 column: self.column,
             })
@@ -60,19 +212,255 @@ impl IPCVisitor {
     }
 }
 
+impl TaskVisitor {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
+        TaskVisitor {
+            field_visitor: FieldVisitor::new(metadata),
+            kind: wire::tasks::task::Kind::Spawn,
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+    pub(crate) fn result(self) -> TaskVisitorResult {
+        TaskVisitorResult {
+            fields: self.field_visitor.result(),
+            kind: self.kind,
+            file: self.file,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+impl ResourceVisitor {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
+        ResourceVisitor {
+            field_visitor: FieldVisitor::new(metadata),
+            kind: None,
+            concrete_type: None,
+            is_internal: false,
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+    pub(crate) fn result(self) -> Option<ResourceVisitorResult> {
+        Some(ResourceVisitorResult {
+            fields: self.field_visitor.result(),
+            kind: self.kind?,
+            concrete_type: self.concrete_type?,
+            is_internal: self.is_internal,
+            file: self.file,
+            line: self.line,
+            column: self.column,
+        })
+    }
+}
+
+impl AsyncOpVisitor {
+    pub(crate) fn result(self) -> Option<String> {
+        self.source
+    }
+}
+
+impl StateUpdateVisitor {
+    /// Returns the name, new value and operation of every updated attribute.
+    pub(crate) fn result(self) -> Vec<(String, wire::field::Value, AttributeOp)> {
+        let ops = self.ops;
+        self.values
+            .into_iter()
+            .map(|(name, value)| {
+                let op = ops
+                    .iter()
+                    .find(|(attribute, _)| *attribute == name)
+                    .map(|(_, op)| *op)
+                    .unwrap_or_default();
+                (name, value, op)
+            })
+            .collect()
+    }
+
+    fn push(&mut self, field: &tracing_core::Field, value: wire::field::Value) {
+        self.values.push((field.name().to_string(), value));
+    }
+}
+
+impl EventVisitor {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
+        EventVisitor {
+            field_visitor: FieldVisitor::new(metadata),
+            name: None,
+            source_window: None,
+            target_window: None,
+            window: None,
+            payload_len: 0,
+        }
+    }
+    pub(crate) fn result(self) -> EventVisitorResult {
+        EventVisitorResult {
+            fields: self.field_visitor.result(),
+            name: self.name,
+            source_window: self.source_window,
+            target_window: self.target_window,
+            window: self.window,
+            payload_len: self.payload_len,
+        }
+    }
+}
+
+impl PayloadVisitor {
+    pub(crate) fn new(metadata: &'static tracing_core::Metadata<'static>) -> Self {
+        PayloadVisitor {
+            field_visitor: FieldVisitor::new(metadata),
+            name: None,
+            value: None,
+        }
+    }
+    pub(crate) fn result(self) -> PayloadVisitorResult {
+        PayloadVisitorResult {
+            fields: self.field_visitor.result(),
+            name: self.name,
+            value: self.value,
+        }
+    }
+}
+
+impl ErrorVisitor {
+    pub(crate) fn result(self) -> Option<String> {
+        self.error
+    }
+}
+
+impl WindowVisitor {
+    /// Returns the label of the window and what happened to it, if `name` is a valid window event.
+    pub(crate) fn result(self, name: &str) -> Option<(String, wire::windows::window_event::Kind)> {
+        use wire::windows::{window_event, Size};
+
+        let size = match (self.width, self.height) {
+            (Some(width), Some(height)) => Some(Size { width, height }),
+            _ => None,
+        };
+
+        let kind = match name {
+            "window.created" => window_event::Kind::Created(window_event::Created {
+                url: self.url.unwrap_or_default(),
+                size,
+            }),
+            "window.focused" => window_event::Kind::Focused(window_event::Focused {
+                focused: self.focused?,
+            }),
+            "window.resized" => window_event::Kind::Resized(window_event::Resized {
+                size: Some(size?),
+            }),
+            "window.closed" => window_event::Kind::Closed(window_event::Closed {}),
+            "webview.navigated" => window_event::Kind::Navigated(window_event::Navigated {
+                url: self.url?,
+            }),
+            _ => return None,
+        };
+
+        Some((self.window?, kind))
+    }
+}
+
+impl WakerVisitor {
+    const WAKE: &'static str = "waker.wake";
+    const WAKE_BY_REF: &'static str = "waker.wake_by_ref";
+    const CLONE: &'static str = "waker.clone";
+    const DROP: &'static str = "waker.drop";
+
+    /// Returns the id of the task span and the waker operation, if the event was a valid waker op.
+    pub(crate) fn result(self) -> Option<(tracing_core::span::Id, WakeOp)> {
+        Some((self.id?, self.op?))
+    }
+}
+
+/// Formats a value recorded through `record_debug` as a plain string.
+///
+/// Strings recorded with `?` are formatted with quotes and escapes by their `Debug` impl, which
+/// would otherwise end up in window labels, event names and the like.
+fn debug_to_string(value: &dyn std::fmt::Debug) -> String {
+    let formatted = format!("{:?}", value);
+    let Some(quoted) = formatted
+        .strip_prefix('"')
+        .and_then(|formatted| formatted.strip_suffix('"'))
+    else {
+        return formatted;
+    };
+
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('r') => unquoted.push('\r'),
+            Some('t') => unquoted.push('\t'),
+            Some('0') => unquoted.push('\0'),
+            Some(c @ ('"' | '\'' | '\\')) => unquoted.push(c),
+            // unicode escapes and anything else are kept as they are
+            Some(c) => {
+                unquoted.push('\\');
+                unquoted.push(c);
+            }
+            None => unquoted.push('\\'),
+        }
+    }
+    unquoted
+}
+
 impl Visit for FieldVisitor {
     fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
-        self.fields.push(wire::Field {
-            metadata_id: Some(self.meta_id.clone()),
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-        })
+        self.push(field, value.into())
+    }
+
+    fn record_f64(&mut self, field: &tracing_core::Field, value: f64) {
+        self.push(field, value.into())
+    }
+
+    fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
+        self.push(field, value.into())
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        self.push(field, value.into())
+    }
+
+    fn record_i128(&mut self, field: &tracing_core::Field, value: i128) {
+        self.push(field, value.into())
+    }
+
+    fn record_u128(&mut self, field: &tracing_core::Field, value: u128) {
+        self.push(field, value.into())
+    }
+
+    fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
+        self.push(field, value.into())
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        self.push(field, value.into())
+    }
+
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    fn record_value(&mut self, field: &tracing_core::Field, value: valuable::Value<'_>) {
+        self.push(
+            field,
+            wire::field::Value::StructuredVal(structured::to_proto(value)),
+        )
     }
 }
 
 impl Visit for IPCVisitor {
     fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
-        self.field_visitor.record_debug(field, value)
+        match field.name() {
+            "window" => self.window = Some(debug_to_string(value)),
+            _ => self.field_visitor.record_debug(field, value),
+        }
     }
 
     fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
@@ -95,7 +483,530 @@ impl Visit for IPCVisitor {
                 };
                 self.kind = Some(kind);
             }
+            "window" => self.window = Some(value.to_string()),
+            _ => self.field_visitor.record_str(field, value),
+        }
+    }
+
+    forward_to_field_visitor!(
+        record_f64(f64),
+        record_i64(i64),
+        record_i128(i128),
+        record_u128(u128),
+        record_bool(bool),
+        #[cfg(all(tracing_unstable, feature = "valuable"))]
+        record_value(valuable::Value<'_>),
+    );
+}
+
+impl Visit for TaskVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            // tokio records the kind using its `Display` impl
+            "kind" => {
+                if debug_to_string(value) == "blocking" {
+                    self.kind = wire::tasks::task::Kind::Blocking;
+                }
+                self.field_visitor.record_debug(field, value)
+            }
+            _ => self.field_visitor.record_debug(field, value),
+        }
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        match field.name() {
+            "loc.line" => self.line = Some(value as u32),
+            "loc.col" => self.column = Some(value as u32),
+            _ => self.field_visitor.record_u64(field, value),
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        match field.name() {
+            "loc.file" => self.file = Some(value.to_string()),
             _ => self.field_visitor.record_str(field, value),
         }
     }
+
+    forward_to_field_visitor!(
+        record_f64(f64),
+        record_i64(i64),
+        record_i128(i128),
+        record_u128(u128),
+        record_bool(bool),
+        #[cfg(all(tracing_unstable, feature = "valuable"))]
+        record_value(valuable::Value<'_>),
+    );
+}
+
+impl Visit for ResourceVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "kind" => self.kind = Some(debug_to_string(value)),
+            "concrete_type" => self.concrete_type = Some(debug_to_string(value)),
+            _ => self.field_visitor.record_debug(field, value),
+        }
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        match field.name() {
+            "loc.line" => self.line = Some(value as u32),
+            "loc.col" => self.column = Some(value as u32),
+            _ => self.field_visitor.record_u64(field, value),
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        match field.name() {
+            "kind" => self.kind = Some(value.to_string()),
+            "concrete_type" => self.concrete_type = Some(value.to_string()),
+            "loc.file" => self.file = Some(value.to_string()),
+            _ => self.field_visitor.record_str(field, value),
+        }
+    }
+
+    fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
+        match field.name() {
+            "is_internal" => self.is_internal = value,
+            _ => self.field_visitor.record_bool(field, value),
+        }
+    }
+
+    forward_to_field_visitor!(
+        record_f64(f64),
+        record_i64(i64),
+        record_i128(i128),
+        record_u128(u128),
+        #[cfg(all(tracing_unstable, feature = "valuable"))]
+        record_value(valuable::Value<'_>),
+    );
+}
+
+impl Visit for AsyncOpVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "source" {
+            self.source = Some(debug_to_string(value));
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        if field.name() == "source" {
+            self.source = Some(value.to_string());
+        }
+    }
+}
+
+impl Visit for StateUpdateVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        self.push(field, value.into())
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        let Some(attribute) = field.name().strip_suffix(".op") else {
+            return self.push(field, value.into());
+        };
+
+        let op = match value {
+            "add" => AttributeOp::Add,
+            "sub" => AttributeOp::Sub,
+            _ => AttributeOp::Override,
+        };
+        self.ops.push((attribute.to_string(), op));
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        self.push(field, value.into())
+    }
+
+    fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
+        self.push(field, value.into())
+    }
+
+    fn record_f64(&mut self, field: &tracing_core::Field, value: f64) {
+        self.push(field, value.into())
+    }
+
+    fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
+        self.push(field, value.into())
+    }
+}
+
+impl Visit for EventVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "event" => self.name = Some(debug_to_string(value)),
+            "source_window" => self.source_window = Some(debug_to_string(value)),
+            "target_window" => self.target_window = Some(debug_to_string(value)),
+            "window" => self.window = Some(debug_to_string(value)),
+            _ => self.field_visitor.record_debug(field, value),
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        match field.name() {
+            "event" => self.name = Some(value.to_string()),
+            "source_window" => self.source_window = Some(value.to_string()),
+            "target_window" => self.target_window = Some(value.to_string()),
+            "window" => self.window = Some(value.to_string()),
+            _ => self.field_visitor.record_str(field, value),
+        }
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        match field.name() {
+            "payload_len" => self.payload_len = value,
+            _ => self.field_visitor.record_u64(field, value),
+        }
+    }
+
+    forward_to_field_visitor!(
+        record_i64(i64),
+        record_f64(f64),
+        record_i128(i128),
+        record_u128(u128),
+        record_bool(bool),
+        #[cfg(all(tracing_unstable, feature = "valuable"))]
+        record_value(valuable::Value<'_>),
+    );
+}
+
+impl PayloadVisitor {
+    const ARG: &'static str = "arg";
+    const VALUE: &'static str = "value";
+}
+
+/// Implements the listed `Visit` methods by capturing the `value` field as a string and
+/// forwarding all other fields to the `field_visitor`.
+macro_rules! capture_value_or_forward {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(&mut self, field: &tracing_core::Field, value: $ty) {
+                match field.name() {
+                    Self::VALUE => self.value = Some(value.to_string()),
+                    _ => self.field_visitor.$method(field, value),
+                }
+            }
+        )*
+    };
+}
+
+impl Visit for PayloadVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            Self::ARG => {
+                self.name = Some(debug_to_string(value));
+                self.field_visitor.record_debug(field, value)
+            }
+            Self::VALUE => self.value = Some(format!("{:?}", value)),
+            _ => self.field_visitor.record_debug(field, value),
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        match field.name() {
+            Self::ARG => {
+                self.name = Some(value.to_string());
+                self.field_visitor.record_str(field, value)
+            }
+            Self::VALUE => self.value = Some(value.to_string()),
+            _ => self.field_visitor.record_str(field, value),
+        }
+    }
+
+    capture_value_or_forward!(
+        record_u64(u64),
+        record_i64(i64),
+        record_f64(f64),
+        record_i128(i128),
+        record_u128(u128),
+        record_bool(bool),
+    );
+}
+
+impl Visit for ErrorVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "error" {
+            self.error = Some(format!("{:?}", value));
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        if field.name() == "error" {
+            self.error = Some(value.to_string());
+        }
+    }
+
+    fn record_error(
+        &mut self,
+        field: &tracing_core::Field,
+        value: &(dyn std::error::Error + 'static),
+    ) {
+        if field.name() == "error" {
+            self.error = Some(value.to_string());
+        }
+    }
+}
+
+impl Visit for WindowVisitor {
+    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "window" => self.window = Some(debug_to_string(value)),
+            "url" => self.url = Some(debug_to_string(value)),
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        match field.name() {
+            "window" => self.window = Some(value.to_string()),
+            "url" => self.url = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        match field.name() {
+            "width" => self.width = Some(value as u32),
+            "height" => self.height = Some(value as u32),
+            _ => {}
+        }
+    }
+
+    fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
+        if field.name() == "focused" {
+            self.focused = Some(value);
+        }
+    }
+}
+
+impl Visit for WakerVisitor {
+    fn record_debug(&mut self, _: &tracing_core::Field, _: &dyn std::fmt::Debug) {
+        // don't care (yet?)
+    }
+
+    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
+        if field.name() == "task.id" {
+            self.id = Some(tracing_core::span::Id::from_u64(value));
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        if field.name() == "op" {
+            self.op = match value {
+                Self::WAKE => Some(WakeOp::Wake { self_wake: false }),
+                Self::WAKE_BY_REF => Some(WakeOp::WakeByRef { self_wake: false }),
+                Self::CLONE => Some(WakeOp::Clone),
+                Self::DROP => Some(WakeOp::Drop),
+                _ => None,
+            };
+        }
+    }
+}
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+mod structured {
+    use valuable::{Valuable, Value};
+    use wire::structured_value::{map::Entry, Kind, List, Map};
+
+    /// Converts a `valuable` value, including all nested values.
+    pub(crate) fn to_proto(value: Value<'_>) -> wire::StructuredValue {
+        let kind = match value {
+            Value::Bool(v) => Kind::BoolVal(v),
+            Value::Char(v) => Kind::StrVal(v.to_string()),
+            Value::F32(v) => Kind::F64Val(v.into()),
+            Value::F64(v) => Kind::F64Val(v),
+            Value::I8(v) => Kind::I64Val(v.into()),
+            Value::I16(v) => Kind::I64Val(v.into()),
+            Value::I32(v) => Kind::I64Val(v.into()),
+            Value::I64(v) => Kind::I64Val(v),
+            Value::I128(v) => Kind::I128Val(v.into()),
+            Value::Isize(v) => Kind::I64Val(v as i64),
+            Value::String(v) => Kind::StrVal(v.to_string()),
+            Value::U8(v) => Kind::U64Val(v.into()),
+            Value::U16(v) => Kind::U64Val(v.into()),
+            Value::U32(v) => Kind::U64Val(v.into()),
+            Value::U64(v) => Kind::U64Val(v),
+            Value::U128(v) => Kind::U128Val(v.into()),
+            Value::Usize(v) => Kind::U64Val(v as u64),
+            Value::Path(v) => Kind::StrVal(v.display().to_string()),
+            Value::Error(v) => Kind::DebugVal(v.to_string()),
+            Value::Listable(v) => Visitor::collect(v, "", false),
+            Value::Mappable(v) => Visitor::collect(v, "", true),
+            Value::Structable(v) => Visitor::collect(v, v.definition().name(), false),
+            Value::Enumerable(v) => Visitor::collect(v, v.variant().name(), false),
+            Value::Tuplable(v) => Visitor::collect(v, "", false),
+            value => Kind::DebugVal(format!("{value:?}")),
+        };
+
+        wire::StructuredValue { kind: Some(kind) }
+    }
+
+    /// Collects the nested values of a list, map, struct, enum variant or tuple.
+    #[derive(Default)]
+    struct Visitor {
+        values: Vec<wire::StructuredValue>,
+        entries: Vec<Entry>,
+    }
+
+    impl Visitor {
+        fn collect(value: &dyn Valuable, name: &str, is_map: bool) -> Kind {
+            let mut visitor = Self::default();
+            value.visit(&mut visitor);
+
+            // structs and enum variants with named fields are visited as entries
+            if is_map || !visitor.entries.is_empty() {
+                Kind::MapVal(Map {
+                    name: name.to_string(),
+                    entries: visitor.entries,
+                })
+            } else {
+                Kind::ListVal(List {
+                    name: name.to_string(),
+                    values: visitor.values,
+                })
+            }
+        }
+    }
+
+    impl valuable::Visit for Visitor {
+        fn visit_value(&mut self, value: Value<'_>) {
+            // every element is a nested value, structs and enum variants included
+            self.values.push(to_proto(value));
+        }
+
+        fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+            for (field, value) in named_values.iter() {
+                self.entries.push(Entry {
+                    key: Some(wire::StructuredValue {
+                        kind: Some(Kind::StrVal(field.name().to_string())),
+                    }),
+                    value: Some(to_proto(*value)),
+                });
+            }
+        }
+
+        fn visit_unnamed_fields(&mut self, values: &[Value<'_>]) {
+            for value in values {
+                self.values.push(to_proto(*value));
+            }
+        }
+
+        fn visit_entry(&mut self, key: Value<'_>, value: Value<'_>) {
+            self.entries.push(Entry {
+                key: Some(to_proto(key)),
+                value: Some(to_proto(value)),
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use valuable::{
+            EnumDef, Enumerable, Fields, NamedField, NamedValues, StructDef, Structable, Variant,
+            VariantDef, Visit,
+        };
+
+        struct User {
+            id: u64,
+        }
+
+        static USER_FIELDS: &[NamedField<'static>] = &[NamedField::new("id")];
+
+        impl Valuable for User {
+            fn as_value(&self) -> Value<'_> {
+                Value::Structable(self)
+            }
+
+            fn visit(&self, visit: &mut dyn Visit) {
+                visit.visit_named_fields(&NamedValues::new(USER_FIELDS, &[Value::U64(self.id)]));
+            }
+        }
+
+        impl Structable for User {
+            fn definition(&self) -> StructDef<'_> {
+                StructDef::new_static("User", Fields::Named(USER_FIELDS))
+            }
+        }
+
+        enum Shape {
+            Point,
+            Circle(f64),
+        }
+
+        static SHAPE_VARIANTS: &[VariantDef<'static>] = &[
+            VariantDef::new("Point", Fields::Unnamed(0)),
+            VariantDef::new("Circle", Fields::Unnamed(1)),
+        ];
+
+        impl Valuable for Shape {
+            fn as_value(&self) -> Value<'_> {
+                Value::Enumerable(self)
+            }
+
+            fn visit(&self, visit: &mut dyn Visit) {
+                match self {
+                    Self::Point => visit.visit_unnamed_fields(&[]),
+                    Self::Circle(radius) => visit.visit_unnamed_fields(&[Value::F64(*radius)]),
+                }
+            }
+        }
+
+        impl Enumerable for Shape {
+            fn definition(&self) -> EnumDef<'_> {
+                EnumDef::new_static("Shape", SHAPE_VARIANTS)
+            }
+
+            fn variant(&self) -> Variant<'_> {
+                match self {
+                    Self::Point => Variant::Static(&SHAPE_VARIANTS[0]),
+                    Self::Circle(_) => Variant::Static(&SHAPE_VARIANTS[1]),
+                }
+            }
+        }
+
+        fn value(kind: Kind) -> wire::StructuredValue {
+            wire::StructuredValue { kind: Some(kind) }
+        }
+
+        fn list(name: &str, values: Vec<wire::StructuredValue>) -> wire::StructuredValue {
+            value(Kind::ListVal(List {
+                name: name.to_string(),
+                values,
+            }))
+        }
+
+        fn user(id: u64) -> wire::StructuredValue {
+            value(Kind::MapVal(Map {
+                name: "User".to_string(),
+                entries: vec![Entry {
+                    key: Some(value(Kind::StrVal("id".to_string()))),
+                    value: Some(value(Kind::U64Val(id))),
+                }],
+            }))
+        }
+
+        #[test]
+        fn converts_lists_of_structs() {
+            let users = vec![User { id: 1 }, User { id: 2 }];
+
+            assert_eq!(to_proto(users.as_value()), list("", vec![user(1), user(2)]));
+        }
+
+        #[test]
+        fn converts_enums_in_lists() {
+            let shapes = vec![Shape::Point, Shape::Circle(1.5)];
+
+            assert_eq!(
+                to_proto(shapes.as_value()),
+                list(
+                    "",
+                    vec![
+                        list("Point", Vec::new()),
+                        list("Circle", vec![value(Kind::F64Val(1.5))]),
+                    ]
+                )
+            );
+        }
+    }
 }
@@ -55,7 +55,11 @@ enum FieldValue {
     Str(InternedStr),
     U64(u64),
     I64(i64),
+    F64(f64),
+    U128(u128),
+    I128(i128),
     Debug(InternedStr),
+    Structured(serde_json::Value),
 }
 
 #[derive(Default, Debug)]
@@ -256,21 +260,13 @@ impl LogRecord {
         meta: &Metadata,
         strings: &mut InternedStrings<R>,
     ) -> Option<Self> {
-        let message = proto.fields.iter().find_map(|field| {
-            if field.name == Some("message".into()) {
-                Some(FieldValue::from_proto(field.value.as_ref()?, strings))
-            } else {
-                None
-            }
-        })?;
-
-        let fields = proto
+        let fields: Vec<Field> = proto
             .fields
             .into_iter()
             .filter_map(|field| {
                 let name = match field.name? {
                     wire::field::Name::StrName(str) => strings.intern_str(&str),
-                    wire::field::Name::NameIdx(idx) => meta.field_names[idx as usize],
+                    wire::field::Name::NameIdx(idx) => *meta.field_names.get(idx as usize)?,
                 };
 
                 Some(Field {
@@ -280,6 +276,12 @@ impl LogRecord {
             })
             .collect();
 
+        let message_name = strings.intern_str("message");
+        let message = fields
+            .iter()
+            .find(|field| field.name == message_name)
+            .map(|field| field.value.clone())?;
+
         Some(Self {
             at: proto.at.clone()?.try_into().ok()?,
             message,
@@ -300,6 +302,10 @@ impl FieldValue {
             wire::field::Value::U64Val(v) => Self::U64(*v),
             wire::field::Value::I64Val(v) => Self::I64(*v),
             wire::field::Value::BoolVal(v) => Self::Bool(*v),
+            wire::field::Value::F64Val(v) => Self::F64(*v),
+            wire::field::Value::U128Val(v) => Self::U128(v.clone().into()),
+            wire::field::Value::I128Val(v) => Self::I128(v.clone().into()),
+            wire::field::Value::StructuredVal(v) => Self::Structured(v.to_json()),
         }
     }
 }
@@ -6,8 +6,6 @@ use tauri::{
     AppHandle, Manager, Runtime, State,
 };
 
-const SERVICE_TYPE: &str = "_cn-devtools._udp.local.";
-
 struct NetworkSessions(Mutex<HashMap<String, SessionInfo>>);
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -40,7 +38,7 @@ async fn start_browse<R: Runtime>(
     daemon: State<'_, ServiceDaemon>,
 ) -> Result<(), ()> {
     // Browse for a service type.
-    let receiver = daemon.browse(SERVICE_TYPE).expect("Failed to browse");
+    let receiver = daemon.browse(wire::zeroconf::SERVICE_TYPE).expect("Failed to browse");
 
     while let Ok(event) = receiver.recv_async().await {
         match event {
@@ -93,7 +91,7 @@ async fn start_browse<R: Runtime>(
 
 #[tauri::command]
 fn stop_browse(daemon: State<'_, ServiceDaemon>) {
-    daemon.stop_browse(SERVICE_TYPE).unwrap();
+    daemon.stop_browse(wire::zeroconf::SERVICE_TYPE).unwrap();
 }
 
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
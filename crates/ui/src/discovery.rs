@@ -0,0 +1,53 @@
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+/// An instrumented app discovered on the local network.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub name: String,
+    pub version: String,
+    pub hostname: String,
+    pub addr: SocketAddr,
+}
+
+/// Browses the local network for instrumented apps for the given duration.
+pub fn browse(timeout: Duration) -> Result<Vec<Session>, mdns_sd::Error> {
+    let daemon = ServiceDaemon::new()?;
+    let receiver = daemon.browse(wire::zeroconf::SERVICE_TYPE)?;
+
+    let mut sessions = Vec::new();
+    let deadline = Instant::now() + timeout;
+    while let Ok(event) = receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+
+        // sessions are advertised with all of their IPv4 addresses, any of them will do
+        let Some(ip) = info.get_addresses().iter().next() else {
+            continue;
+        };
+
+        let (name, _) = info
+            .get_fullname()
+            .split_once('.')
+            .unwrap_or((info.get_fullname(), ""));
+
+        sessions.push(Session {
+            name: name.to_string(),
+            version: info
+                .get_property_val_str("VERSION")
+                .unwrap_or_default()
+                .to_string(),
+            hostname: info.get_hostname().to_string(),
+            addr: SocketAddr::new(IpAddr::from(*ip), info.get_port()),
+        });
+    }
+
+    let _ = daemon.shutdown();
+
+    Ok(sessions)
+}
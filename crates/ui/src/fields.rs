@@ -0,0 +1,27 @@
+/// Formats a field value the way `tracing_subscriber`'s fmt layer does.
+pub fn to_string(value: &wire::field::Value) -> String {
+    match value {
+        wire::field::Value::DebugVal(v) | wire::field::Value::StrVal(v) => v.clone(),
+        wire::field::Value::U64Val(v) => v.to_string(),
+        wire::field::Value::I64Val(v) => v.to_string(),
+        wire::field::Value::BoolVal(v) => v.to_string(),
+        wire::field::Value::F64Val(v) => v.to_string(),
+        wire::field::Value::U128Val(v) => u128::from(v.clone()).to_string(),
+        wire::field::Value::I128Val(v) => i128::from(v.clone()).to_string(),
+        wire::field::Value::StructuredVal(v) => v.to_json().to_string(),
+    }
+}
+
+pub fn to_json(value: &wire::field::Value) -> serde_json::Value {
+    match value {
+        wire::field::Value::DebugVal(v) | wire::field::Value::StrVal(v) => v.clone().into(),
+        wire::field::Value::U64Val(v) => (*v).into(),
+        wire::field::Value::I64Val(v) => (*v).into(),
+        wire::field::Value::BoolVal(v) => (*v).into(),
+        wire::field::Value::F64Val(v) => (*v).into(),
+        // JSON numbers can't represent these precisely
+        wire::field::Value::U128Val(v) => u128::from(v.clone()).to_string().into(),
+        wire::field::Value::I128Val(v) => i128::from(v.clone()).to_string().into(),
+        wire::field::Value::StructuredVal(v) => v.to_json(),
+    }
+}
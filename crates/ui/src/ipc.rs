@@ -0,0 +1,104 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, IsTerminal},
+    net::SocketAddr,
+    time::Duration,
+};
+use wire::instrument::{instrument_client::InstrumentClient, InstrumentRequest, Interests};
+
+/// Prints a table of the stats of all commands, updated whenever requests complete.
+///
+/// With `json`, every change of a command's stats is printed as a separate line instead.
+pub async fn watch(addr: SocketAddr, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = InstrumentClient::connect(format!("http://{addr}")).await?;
+
+    let req = InstrumentRequest::new_with_interests(Interests::Ipc);
+    let mut stream = client.watch_updates(req).await?.into_inner();
+
+    // redraw the table in place when watching it live, keep the history when piped
+    let redraw = io::stdout().is_terminal() && !json;
+
+    let mut commands = BTreeMap::new();
+    while let Some(update) = stream.message().await? {
+        let Some(ipc_update) = update.ipc_update else {
+            continue;
+        };
+        if ipc_update.command_stats_update.is_empty() {
+            continue;
+        }
+
+        if json {
+            for (cmd, stats) in &ipc_update.command_stats_update {
+                println!("{}", to_json(cmd, stats));
+            }
+            continue;
+        }
+
+        commands.extend(ipc_update.command_stats_update);
+
+        if redraw {
+            print!("\x1b[2J\x1b[H");
+        }
+        print_table(&commands);
+    }
+
+    Ok(())
+}
+
+fn print_table(commands: &BTreeMap<String, wire::ipc::CommandStats>) {
+    let width = commands.keys().map(String::len).max().unwrap_or(0).max(7);
+
+    println!(
+        "{:<width$} {:>8} {:>8} {:>10} {:>10} {:>10}",
+        "COMMAND", "CALLS", "ERRORS", "P50", "P95", "P99"
+    );
+    for (cmd, stats) in commands {
+        let inner = stats.inner.as_ref();
+        let percentile = |p: fn(&wire::ipc::Durations) -> Option<&prost_types::Duration>| {
+            inner
+                .and_then(p)
+                .and_then(duration)
+                .map_or_else(|| "-".to_string(), |d| format!("{d:.2?}"))
+        };
+
+        println!(
+            "{:<width$} {:>8} {:>8} {:>10} {:>10} {:>10}",
+            cmd,
+            stats.calls,
+            stats.errors,
+            percentile(|d| d.p50.as_ref()),
+            percentile(|d| d.p95.as_ref()),
+            percentile(|d| d.p99.as_ref()),
+        );
+    }
+    println!();
+}
+
+fn duration(duration: &prost_types::Duration) -> Option<Duration> {
+    duration.clone().try_into().ok()
+}
+
+fn to_json(cmd: &str, stats: &wire::ipc::CommandStats) -> serde_json::Value {
+    // durations are in seconds
+    let durations = |durations: Option<&wire::ipc::Durations>| {
+        let percentile =
+            |p: Option<&prost_types::Duration>| p.and_then(duration).map(|d| d.as_secs_f64());
+
+        durations.map(|durations| {
+            serde_json::json!({
+                "p50": percentile(durations.p50.as_ref()),
+                "p95": percentile(durations.p95.as_ref()),
+                "p99": percentile(durations.p99.as_ref()),
+            })
+        })
+    };
+
+    serde_json::json!({
+        "command": cmd,
+        "calls": stats.calls,
+        "errors": stats.errors,
+        "deserialize_request": durations(stats.deserialize_request.as_ref()),
+        "serialize_response": durations(stats.serialize_response.as_ref()),
+        "inner": durations(stats.inner.as_ref()),
+    })
+}
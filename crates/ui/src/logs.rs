@@ -0,0 +1,227 @@
+use crate::fields;
+use std::{collections::HashMap, net::SocketAddr};
+use wire::instrument::{instrument_client::InstrumentClient, InstrumentRequest, Interests};
+
+/// Prints the log events of the app as they are emitted, starting with the recent history.
+///
+/// `log_filter` is evaluated by the app, so filtered events never cross the connection.
+pub async fn tail(
+    addr: SocketAddr,
+    log_filter: String,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = InstrumentClient::connect(format!("http://{addr}")).await?;
+
+    let req = InstrumentRequest::new_with_interests(Interests::Trace | Interests::Metadata)
+        .with_log_filter(log_filter);
+    let mut stream = client.watch_updates(req).await?.into_inner();
+
+    let mut metadata = HashMap::new();
+    let unknown = unknown_metadata();
+    while let Some(update) = stream.message().await? {
+        // metadata is registered before the first event referencing it, unless it was dropped
+        if let Some(new_metadata) = update.new_metadata {
+            for new in new_metadata.metadata {
+                if let (Some(id), Some(meta)) = (new.id, new.metadata) {
+                    metadata.insert(id.id, meta);
+                }
+            }
+        }
+
+        let Some(log_update) = update.log_update else {
+            continue;
+        };
+
+        for event in &log_update.new_events {
+            let meta = event
+                .metadata_id
+                .as_ref()
+                .and_then(|id| metadata.get(&id.id))
+                .unwrap_or(&unknown);
+
+            if json {
+                println!("{}", to_json(event, meta));
+            } else {
+                println!("{}", format(event, meta));
+            }
+        }
+
+        if log_update.dropped_events > 0 {
+            eprintln!(
+                "{} log events were dropped by the app",
+                log_update.dropped_events
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The metadata used for events whose metadata registration was lost, e.g. because the app
+/// dropped it.
+fn unknown_metadata() -> wire::Metadata {
+    wire::Metadata {
+        target: "<unknown>".to_string(),
+        level: -1,
+        ..Default::default()
+    }
+}
+
+fn level(meta: &wire::Metadata) -> &'static str {
+    wire::metadata::Level::from_i32(meta.level).map_or("UNKNOWN", |level| level.as_str_name())
+}
+
+/// Formats an event like `tracing_subscriber`'s fmt layer.
+fn format(event: &wire::log::Event, meta: &wire::Metadata) -> String {
+    let mut line = String::new();
+
+    if let Some(at) = &event.at {
+        line.push_str(&format!("{at} "));
+    }
+    line.push_str(&format!("{:>5} {}:", level(meta), meta.target));
+
+    let mut fields = Vec::new();
+    for field in &event.fields {
+        let (Some(name), Some(value)) = (field.name(meta), &field.value) else {
+            continue;
+        };
+
+        if name == "message" {
+            line.push_str(&format!(" {}", fields::to_string(value)));
+        } else {
+            fields.push(format!("{name}={}", fields::to_string(value)));
+        }
+    }
+
+    for field in fields {
+        line.push(' ');
+        line.push_str(&field);
+    }
+
+    line
+}
+
+fn to_json(event: &wire::log::Event, meta: &wire::Metadata) -> serde_json::Value {
+    let fields: serde_json::Map<_, _> = event
+        .fields
+        .iter()
+        .filter_map(|field| {
+            let name = field.name(meta)?;
+            Some((name.to_string(), fields::to_json(field.value.as_ref()?)))
+        })
+        .collect();
+
+    let location = meta.location.as_ref();
+
+    serde_json::json!({
+        "at": event.at.as_ref().map(ToString::to_string),
+        "level": level(meta),
+        "target": meta.target,
+        "file": location.and_then(|location| location.file.as_ref()),
+        "line": location.and_then(|location| location.line),
+        "fields": fields,
+        "spans": event.parents.iter().map(|span| span.id).collect::<Vec<_>>(),
+        "ipc_request": event.ipc_request.as_ref().map(|id| id.id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> wire::Metadata {
+        wire::Metadata {
+            name: "event src/db.rs:7".to_string(),
+            target: "app::db".to_string(),
+            location: Some(wire::Location {
+                file: Some("src/db.rs".to_string()),
+                module_path: Some("app::db".to_string()),
+                line: Some(7),
+                column: None,
+            }),
+            kind: wire::metadata::Kind::Event as i32,
+            level: wire::metadata::Level::Info as i32,
+            field_names: vec!["message".to_string(), "rows".to_string()],
+        }
+    }
+
+    fn event() -> wire::log::Event {
+        let field = |name, value| wire::Field {
+            name: Some(name),
+            value: Some(value),
+            metadata_id: None,
+        };
+
+        wire::log::Event {
+            metadata_id: Some(wire::MetaId { id: 1 }),
+            fields: vec![
+                field(
+                    wire::field::Name::NameIdx(0),
+                    wire::field::Value::DebugVal("querying".to_string()),
+                ),
+                field(wire::field::Name::NameIdx(1), wire::field::Value::U64Val(3)),
+                field(
+                    wire::field::Name::StrName("user".to_string()),
+                    wire::field::Value::StrVal("ann".to_string()),
+                ),
+            ],
+            at: Some(prost_types::Timestamp {
+                seconds: 0,
+                nanos: 0,
+            }),
+            parents: vec![wire::SpanId { id: 4 }],
+            ipc_request: Some(wire::Id { id: 9 }),
+        }
+    }
+
+    #[test]
+    fn formats_events() {
+        assert_eq!(
+            format(&event(), &metadata()),
+            "1970-01-01T00:00:00Z  INFO app::db: querying rows=3 user=ann"
+        );
+    }
+
+    #[test]
+    fn formats_events_with_unknown_metadata() {
+        // only fields sent with their name can be printed
+        assert_eq!(
+            format(&event(), &unknown_metadata()),
+            "1970-01-01T00:00:00Z UNKNOWN <unknown>: user=ann"
+        );
+    }
+
+    #[test]
+    fn converts_events_to_json() {
+        assert_eq!(
+            to_json(&event(), &metadata()),
+            serde_json::json!({
+                "at": "1970-01-01T00:00:00Z",
+                "level": "INFO",
+                "target": "app::db",
+                "file": "src/db.rs",
+                "line": 7,
+                "fields": { "message": "querying", "rows": 3, "user": "ann" },
+                "spans": [4],
+                "ipc_request": 9,
+            })
+        );
+    }
+
+    #[test]
+    fn converts_events_with_unknown_metadata_to_json() {
+        assert_eq!(
+            to_json(&event(), &unknown_metadata()),
+            serde_json::json!({
+                "at": "1970-01-01T00:00:00Z",
+                "level": "UNKNOWN",
+                "target": "<unknown>",
+                "file": null,
+                "line": null,
+                "fields": { "user": "ann" },
+                "spans": [4],
+                "ipc_request": 9,
+            })
+        );
+    }
+}
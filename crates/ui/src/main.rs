@@ -1,3 +1,236 @@
+//! A terminal client for the devtools, e.g. to inspect apps over SSH or from scripts.
+
+mod discovery;
+mod fields;
+mod ipc;
+mod logs;
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    process,
+    time::Duration,
+};
+
+const USAGE: &str = "usage: ui <command> [options]
+
+commands:
+    sessions            list the instrumented apps on the local network
+    logs                print log events as they are emitted
+    ipc                 show the stats of all IPC commands, updated live
+
+options:
+    --addr <addr>       connect to the app at this address (default: 127.0.0.1:6669)
+    --session <name>    connect to the app with this name, discovered through mDNS
+    --level <level>     only print log events up to this level
+    --target <target>   only print log events whose target starts with this, may be repeated
+    --json              print JSON lines instead of human readable output";
+
+/// The port apps listen on by default.
+const DEFAULT_PORT: u16 = 6669;
+
+/// How long to wait for apps to respond to mDNS queries.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum Command {
+    Sessions,
+    Logs,
+    Ipc,
+}
+
+struct Args {
+    command: Command,
+    addr: Option<SocketAddr>,
+    session: Option<String>,
+    level: Option<String>,
+    targets: Vec<String>,
+    json: bool,
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .expect("runtime initialization failed");
+
+    if let Err(e) = runtime.block_on(run(args)) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    match args.command {
+        Command::Sessions => list_sessions(args.json),
+        Command::Logs => logs::tail(args.addr()?, args.log_filter(), args.json).await,
+        Command::Ipc => ipc::watch(args.addr()?, args.json).await,
+    }
+}
+
+fn list_sessions(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    for session in discovery::browse(DISCOVERY_TIMEOUT)? {
+        if json {
+            let session = serde_json::json!({
+                "name": session.name,
+                "version": session.version,
+                "hostname": session.hostname,
+                "addr": session.addr.to_string(),
+            });
+            println!("{session}");
+        } else {
+            println!(
+                "{} {} on {} at {}",
+                session.name, session.version, session.hostname, session.addr
+            );
+        }
+    }
+
+    Ok(())
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            Some("sessions") => Command::Sessions,
+            Some("logs") => Command::Logs,
+            Some("ipc") => Command::Ipc,
+            Some(command) => return Err(format!("unknown command `{command}`")),
+            None => return Err("missing command".to_string()),
+        };
+
+        let mut parsed = Self {
+            command,
+            addr: None,
+            session: None,
+            level: None,
+            targets: Vec::new(),
+            json: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+            match arg.as_str() {
+                "--addr" => {
+                    let addr = value()?
+                        .parse()
+                        .map_err(|e| format!("invalid address: {e}"))?;
+                    parsed.addr = Some(addr);
+                }
+                "--session" => parsed.session = Some(value()?),
+                "--level" => {
+                    let level = value()?.to_lowercase();
+                    if !["error", "warn", "info", "debug", "trace"].contains(&level.as_str()) {
+                        return Err(format!("invalid level `{level}`"));
+                    }
+                    parsed.level = Some(level);
+                }
+                "--target" => parsed.targets.push(value()?),
+                "--json" => parsed.json = true,
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the address of the app to connect to, discovering it by name if necessary.
+    fn addr(&self) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+        if let Some(addr) = self.addr {
+            return Ok(addr);
+        }
+
+        let Some(name) = &self.session else {
+            return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), DEFAULT_PORT));
+        };
+
+        let session = discovery::browse(DISCOVERY_TIMEOUT)?
+            .into_iter()
+            .find(|session| session.name == *name)
+            .ok_or(format!("no session named `{name}` found"))?;
+
+        Ok(session.addr)
+    }
+
+    /// Builds the log filter directives sent to the app, see `InstrumentRequest.log_filter`.
+    fn log_filter(&self) -> String {
+        if self.targets.is_empty() {
+            // an empty filter sends all events
+            return self.level.clone().unwrap_or_default();
+        }
+
+        let level = self.level.as_deref().unwrap_or("trace");
+
+        self.targets
+            .iter()
+            .map(|target| format!("{target}={level}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn parses_options() {
+        let args = parse(&[
+            "logs",
+            "--addr",
+            "10.0.0.1:7000",
+            "--level",
+            "WARN",
+            "--target",
+            "app",
+            "--json",
+        ])
+        .unwrap();
+
+        assert!(matches!(args.command, Command::Logs));
+        assert_eq!(args.addr, Some("10.0.0.1:7000".parse().unwrap()));
+        assert_eq!(args.level.as_deref(), Some("warn"));
+        assert_eq!(args.targets, ["app"]);
+        assert!(args.json);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+
+        assert_eq!(error(&[]), "missing command");
+        assert_eq!(error(&["tail"]), "unknown command `tail`");
+        assert_eq!(
+            error(&["logs", "--verbose"]),
+            "unexpected argument `--verbose`"
+        );
+        assert_eq!(error(&["logs", "--level"]), "missing value for `--level`");
+        assert_eq!(error(&["logs", "--level", "loud"]), "invalid level `loud`");
+        assert!(error(&["ipc", "--addr", "localhost"]).starts_with("invalid address"));
+    }
+
+    #[test]
+    fn builds_log_filters() {
+        let log_filter = |args: &[&str]| parse(args).unwrap().log_filter();
+
+        assert_eq!(log_filter(&["logs"]), "");
+        assert_eq!(log_filter(&["logs", "--level", "debug"]), "debug");
+        assert_eq!(log_filter(&["logs", "--target", "app"]), "app=trace");
+        assert_eq!(
+            log_filter(&["logs", "--level", "info", "--target", "app", "--target", "tauri"]),
+            "app=info,tauri=info"
+        );
+    }
 }
@@ -1,29 +1,12 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let root_dir = PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
     let proto_dir = root_dir.join("proto");
-    let proto_ext = std::ffi::OsStr::new("proto");
-    let proto_files = fs::read_dir(&proto_dir).and_then(|dir| {
-        dir.filter_map(|entry| {
-            (|| {
-                let entry = entry?;
-                if entry.file_type()?.is_dir() {
-                    return Ok(None);
-                }
-
-                let path = entry.path();
-                if path.extension() != Some(proto_ext) {
-                    return Ok(None);
-                }
-
-                Ok(Some(path))
-            })()
-            .transpose()
-        })
-        .collect::<Result<Vec<_>, _>>()
-    });
-    let proto_files = match proto_files {
+    let proto_files = match list_proto_files(&proto_dir) {
         Ok(files) => files,
         Err(error) => panic!("failed to list proto files: {error}"),
     };
@@ -37,3 +20,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Lists all proto files in `dir`, including the vendored ones in subdirectories.
+fn list_proto_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let proto_ext = std::ffi::OsStr::new("proto");
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            files.extend(list_proto_files(&path)?);
+        } else if path.extension() == Some(proto_ext) {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
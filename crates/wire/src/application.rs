@@ -1,3 +1,5 @@
+use crate::windows;
+
 mod generated {
     #![allow(clippy::all)]
     #![allow(warnings)]
@@ -113,6 +113,54 @@ impl From<u64> for field::Value {
     }
 }
 
+impl From<f64> for field::Value {
+    fn from(val: f64) -> Self {
+        field::Value::F64Val(val)
+    }
+}
+
+impl From<u128> for field::Value {
+    fn from(val: u128) -> Self {
+        field::Value::U128Val(val.into())
+    }
+}
+
+impl From<i128> for field::Value {
+    fn from(val: i128) -> Self {
+        field::Value::I128Val(val.into())
+    }
+}
+
+impl From<u128> for U128 {
+    fn from(val: u128) -> Self {
+        U128 {
+            high: (val >> 64) as u64,
+            low: val as u64,
+        }
+    }
+}
+
+impl From<U128> for u128 {
+    fn from(val: U128) -> Self {
+        (u128::from(val.high) << 64) | u128::from(val.low)
+    }
+}
+
+impl From<i128> for I128 {
+    fn from(val: i128) -> Self {
+        I128 {
+            high: (val >> 64) as i64,
+            low: val as u64,
+        }
+    }
+}
+
+impl From<I128> for i128 {
+    fn from(val: I128) -> Self {
+        (i128::from(val.high) << 64) | i128::from(val.low)
+    }
+}
+
 impl From<bool> for field::Value {
     fn from(val: bool) -> Self {
         field::Value::BoolVal(val)
@@ -135,4 +183,58 @@ impl From<&dyn std::fmt::Debug> for field::Value {
     fn from(val: &dyn std::fmt::Debug) -> Self {
         field::Value::DebugVal(format!("{:?}", val))
     }
-}
\ No newline at end of file
+}
+
+impl Field {
+    /// Returns the name of the field, which is either sent inline or as an index into the field
+    /// names of its `metadata`.
+    pub fn name<'a>(&'a self, metadata: &'a Metadata) -> Option<&'a str> {
+        match self.name.as_ref()? {
+            field::Name::StrName(name) => Some(name),
+            field::Name::NameIdx(idx) => {
+                metadata.field_names.get(*idx as usize).map(String::as_str)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl StructuredValue {
+    /// Converts the value into JSON, names of structs and enum variants are dropped.
+    pub fn to_json(&self) -> serde_json::Value {
+        use structured_value::Kind;
+
+        match &self.kind {
+            None => serde_json::Value::Null,
+            Some(Kind::DebugVal(v) | Kind::StrVal(v)) => v.clone().into(),
+            Some(Kind::U64Val(v)) => (*v).into(),
+            Some(Kind::I64Val(v)) => (*v).into(),
+            Some(Kind::BoolVal(v)) => (*v).into(),
+            Some(Kind::F64Val(v)) => (*v).into(),
+            // JSON numbers can't represent these precisely
+            Some(Kind::U128Val(v)) => u128::from(v.clone()).to_string().into(),
+            Some(Kind::I128Val(v)) => i128::from(v.clone()).to_string().into(),
+            Some(Kind::ListVal(list)) => list.values.iter().map(Self::to_json).collect(),
+            Some(Kind::MapVal(map)) => map
+                .entries
+                .iter()
+                .map(|entry| {
+                    let key = match entry.key.as_ref().map(Self::to_json) {
+                        Some(serde_json::Value::String(key)) => key,
+                        Some(key) => key.to_string(),
+                        None => String::new(),
+                    };
+
+                    let value = entry
+                        .value
+                        .as_ref()
+                        .map(Self::to_json)
+                        .unwrap_or_default();
+
+                    (key, value)
+                })
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use crate::common;
+
+mod generated {
+    #![allow(clippy::all)]
+    #![allow(warnings)]
+    
+    tonic::include_proto!("rs.tauri.devtools.events");
+}
+
+pub use generated::*;
\ No newline at end of file
@@ -1,6 +1,12 @@
 use crate::common;
 use crate::log;
 use crate::ipc;
+use crate::tasks;
+use crate::spans;
+use crate::events;
+use crate::windows;
+use crate::metrics;
+use crate::panics;
 
 mod generated {
     #![allow(clippy::all)]
@@ -11,13 +17,42 @@ mod generated {
 
 pub use generated::*;
 
+/// The version of the instrument protocol implemented by this crate, see `InstrumentRequest.protocol_version`.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 impl InstrumentRequest {
     pub fn new() -> Self {
-        Self { interests: Interests::all().bits() }
+        Self::new_with_interests(Interests::all())
     }
 
     pub fn new_with_interests(interests: Interests) -> Self {
-        Self { interests: interests.bits() }
+        Self {
+            interests: interests.bits(),
+            log_filter: String::new(),
+            protocol_version: PROTOCOL_VERSION,
+        }
+    }
+
+    /// Only receive log events matching the given `EnvFilter`-style directives.
+    pub fn with_log_filter(self, log_filter: impl Into<String>) -> Self {
+        Self {
+            log_filter: log_filter.into(),
+            ..self
+        }
+    }
+
+    /// Returns the requested interests, or `None` if they contain unknown bits.
+    ///
+    /// Clients built before `protocol_version` was introduced always received IPC requests,
+    /// so they keep receiving them.
+    pub fn interests(&self) -> Option<Interests> {
+        let interests = Interests::from_bits(self.interests)?;
+
+        if self.protocol_version == 0 {
+            Some(interests | Interests::Ipc)
+        } else {
+            Some(interests)
+        }
     }
 }
 
@@ -32,5 +67,12 @@ bitflags::bitflags! {
     pub struct Interests: u32 {
         const Trace = 0b0001;
         const Metadata = 0b0010;
+        const Ipc = 0b0100;
+        const Tasks = 0b1000;
+        const Spans = 0b1_0000;
+        const Events = 0b10_0000;
+        const Windows = 0b100_0000;
+        const Metrics = 0b1000_0000;
+        const Panics = 0b1_0000_0000;
     }
 }
\ No newline at end of file
@@ -4,4 +4,12 @@ pub mod instrument;
 pub mod log;
 pub mod application;
 pub mod ipc;
-pub mod tasks;
\ No newline at end of file
+pub mod tasks;
+pub mod spans;
+pub mod events;
+pub mod windows;
+pub mod metrics;
+pub mod panics;
+pub mod recording;
+pub mod otlp;
+pub mod zeroconf;
\ No newline at end of file
@@ -0,0 +1,102 @@
+//! A subset of the OpenTelemetry protocol (OTLP), used to export spans and log events to collectors.
+//!
+//! The modules mirror the `opentelemetry.proto` packages, so the generated code can refer to its dependencies.
+
+mod generated {
+    #![allow(clippy::all)]
+    #![allow(warnings)]
+
+    pub mod common {
+        pub mod v1 {
+            tonic::include_proto!("opentelemetry.proto.common.v1");
+        }
+    }
+
+    pub mod resource {
+        pub mod v1 {
+            tonic::include_proto!("opentelemetry.proto.resource.v1");
+        }
+    }
+
+    pub mod trace {
+        pub mod v1 {
+            tonic::include_proto!("opentelemetry.proto.trace.v1");
+        }
+    }
+
+    pub mod logs {
+        pub mod v1 {
+            tonic::include_proto!("opentelemetry.proto.logs.v1");
+        }
+    }
+
+    pub mod collector {
+        pub mod trace {
+            pub mod v1 {
+                tonic::include_proto!("opentelemetry.proto.collector.trace.v1");
+            }
+        }
+
+        pub mod logs {
+            pub mod v1 {
+                tonic::include_proto!("opentelemetry.proto.collector.logs.v1");
+            }
+        }
+    }
+}
+
+pub use generated::*;
+
+use common::v1::{any_value, AnyValue, ArrayValue, KeyValue, KeyValueList};
+
+/// Converts a structured value into arrays and maps, names of structs and enum variants are dropped.
+impl From<&crate::StructuredValue> for AnyValue {
+    fn from(value: &crate::StructuredValue) -> Self {
+        use crate::structured_value::Kind;
+
+        let value = match &value.kind {
+            None => return AnyValue { value: None },
+            Some(Kind::DebugVal(v) | Kind::StrVal(v)) => any_value::Value::StringValue(v.clone()),
+            Some(Kind::U64Val(v)) => match i64::try_from(*v) {
+                Ok(v) => any_value::Value::IntValue(v),
+                Err(_) => any_value::Value::StringValue(v.to_string()),
+            },
+            Some(Kind::I64Val(v)) => any_value::Value::IntValue(*v),
+            Some(Kind::BoolVal(v)) => any_value::Value::BoolValue(*v),
+            Some(Kind::F64Val(v)) => any_value::Value::DoubleValue(*v),
+            // OTLP integers are 64 bit, so these are sent as strings to keep them precise
+            Some(Kind::U128Val(v)) => {
+                any_value::Value::StringValue(u128::from(v.clone()).to_string())
+            }
+            Some(Kind::I128Val(v)) => {
+                any_value::Value::StringValue(i128::from(v.clone()).to_string())
+            }
+            Some(Kind::ListVal(list)) => any_value::Value::ArrayValue(ArrayValue {
+                values: list.values.iter().map(Into::into).collect(),
+            }),
+            Some(Kind::MapVal(map)) => any_value::Value::KvlistValue(KeyValueList {
+                values: map
+                    .entries
+                    .iter()
+                    .map(|entry| KeyValue {
+                        key: entry.key.as_ref().map(structured_key).unwrap_or_default(),
+                        value: entry.value.as_ref().map(Into::into),
+                    })
+                    .collect(),
+            }),
+        };
+
+        AnyValue { value: Some(value) }
+    }
+}
+
+/// OTLP map keys are strings, so other keys are converted to their string representation.
+fn structured_key(key: &crate::StructuredValue) -> String {
+    match AnyValue::from(key).value {
+        Some(any_value::Value::StringValue(key)) => key,
+        Some(any_value::Value::IntValue(key)) => key.to_string(),
+        Some(any_value::Value::BoolValue(key)) => key.to_string(),
+        Some(any_value::Value::DoubleValue(key)) => key.to_string(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,31 @@
+use crate::application;
+use std::path::{Path, PathBuf};
+
+mod generated {
+    #![allow(clippy::all)]
+    #![allow(warnings)]
+
+    tonic::include_proto!("rs.tauri.devtools.recording");
+}
+
+pub use generated::*;
+
+/// The version of the recording format written to `Header.protocol_version`.
+///
+/// This has to be incremented whenever a change to the protos would prevent older
+/// recordings from being decoded.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Returns the path of the recording file with the given index.
+///
+/// The first file is written to `path` itself and later ones get the index appended
+/// (e.g. `session.rec`, `session.rec.1`, `session.rec.2`).
+pub fn part_path(path: &Path, part: u32) -> PathBuf {
+    if part == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{part}"));
+    path.with_file_name(file_name)
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+mod generated {
+    #![allow(clippy::all)]
+    #![allow(warnings)]
+
+    tonic::include_proto!("rs.tauri.devtools.windows");
+}
+
+pub use generated::*;
\ No newline at end of file
@@ -0,0 +1,48 @@
+//! Advertising devtools servers through mDNS, shared by instrumented apps and replays.
+
+/// The mDNS service type devtools servers are advertised as.
+pub const SERVICE_TYPE: &str = "_cn-devtools._udp.local.";
+
+/// Describes a devtools server listening on `port` of this machine, so clients discover it.
+///
+/// The service is published under the machine's hostname and IPv4 addresses, with the OS and
+/// architecture of this machine and the version, description and authors of `package_info`
+/// as properties.
+#[cfg(feature = "zeroconf")]
+pub fn service_info(
+    instance_name: &str,
+    port: u16,
+    package_info: &crate::application::PackageInfo,
+) -> Result<mdns_sd::ServiceInfo, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    use std::net::IpAddr;
+
+    let hostname = hostname::get()?.to_string_lossy().to_string();
+
+    let host_ipv4 = if_addrs::get_if_addrs()?
+        .iter()
+        .filter_map(|if_addr| match if_addr.addr.ip() {
+            IpAddr::V4(ipv4) => Some(ipv4.to_string()),
+            IpAddr::V6(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let properties = [
+        ("OS", std::env::consts::OS),
+        ("ARCH", std::env::consts::ARCH),
+        ("VERSION", &package_info.version),
+        ("DESCRIPTION", &package_info.description),
+        ("AUTHORS", &package_info.authors),
+    ];
+
+    let service = mdns_sd::ServiceInfo::new(
+        SERVICE_TYPE,
+        instance_name,
+        &hostname,
+        host_ipv4,
+        port,
+        &properties[..],
+    )?;
+
+    Ok(service)
+}